@@ -0,0 +1,112 @@
+//! Wizard for calibrating turntable move timings.
+
+use eframe::egui;
+
+use crate::app::worker::{
+    CalibrationProgress, CompletionDetection, TurntableWorkerCommand, TurntableWorkerState,
+};
+use crate::turntable::calibration::{Axis, AxisTiming};
+
+/// Show the calibration window, returning any command to send to the turntable worker.
+pub(super) fn calibration_window(
+    ctx: &egui::Context,
+    open: &mut bool,
+    detection: &mut CompletionDetection,
+    worker_state: &TurntableWorkerState,
+    progress: Option<&CalibrationProgress>,
+) -> Option<TurntableWorkerCommand> {
+    let mut command = None;
+    egui::Window::new("Calibrate Timing")
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(
+                "Times a series of moves on each axis, so move durations can be estimated \
+                 for this turntable. Clear the table before starting.",
+            );
+            ui.add_space(8.0);
+            match worker_state {
+                TurntableWorkerState::Calibrating(CalibrationProgress {
+                    result: None,
+                    axis,
+                    move_index,
+                    total_moves,
+                    awaiting_confirmation,
+                }) => {
+                    ui.label(format!(
+                        "{} move {} of {}",
+                        axis_name(*axis),
+                        move_index + 1,
+                        total_moves
+                    ));
+                    ui.horizontal(|ui| {
+                        if *awaiting_confirmation {
+                            if ui.button("Stopped moving").clicked() {
+                                command = Some(TurntableWorkerCommand::ConfirmMoveComplete);
+                            }
+                        } else {
+                            ui.spinner();
+                        }
+                        if ui.button("Cancel").clicked() {
+                            command = Some(TurntableWorkerCommand::CancelCalibration);
+                        }
+                    });
+                }
+                _ => {
+                    ui.radio_value(
+                        detection,
+                        CompletionDetection::AngleQuery,
+                        "Detect rotation by querying angle",
+                    );
+                    ui.radio_value(
+                        detection,
+                        CompletionDetection::UserConfirmation,
+                        "Confirm each move manually",
+                    );
+                    ui.label("Tilt moves always need confirming.");
+                    ui.add_space(8.0);
+                    let enabled = matches!(worker_state, TurntableWorkerState::Connected);
+                    if ui
+                        .add_enabled(enabled, egui::Button::new("Start"))
+                        .clicked()
+                    {
+                        command = Some(TurntableWorkerCommand::Calibrate {
+                            detection: *detection,
+                        });
+                    }
+                }
+            }
+
+            if let Some(result) = progress.and_then(|progress| progress.result.as_ref()) {
+                ui.add_space(8.0);
+                match result {
+                    Ok(timing) => {
+                        ui.label("Calibration saved:");
+                        for axis in [Axis::Rotation, Axis::Tilt] {
+                            ui.label(timing_text(axis, timing.axis(axis)));
+                        }
+                    }
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                }
+            }
+        });
+    command
+}
+
+fn axis_name(axis: Axis) -> &'static str {
+    match axis {
+        Axis::Rotation => "Rotation",
+        Axis::Tilt => "Tilt",
+    }
+}
+
+fn timing_text(axis: Axis, timing: &AxisTiming) -> String {
+    format!(
+        "{}: {:.0} ms + {:.1} ms/deg",
+        axis_name(axis),
+        timing.constant_ms,
+        timing.ms_per_deg
+    )
+}
@@ -0,0 +1,169 @@
+//! Raw protocol console, for exploring turntable firmware.
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eframe::egui::{self, Key};
+use rfd::FileDialog;
+
+use crate::app::worker::{TurntableWorkerCommand, TurntableWorkerState};
+use crate::turntable::{Traffic, TrafficKind};
+
+/// Oldest traffic is dropped beyond this many entries.
+const MAX_TRANSCRIPT_LEN: usize = 10_000;
+
+/// Protocol console UI state.
+#[derive(Default)]
+pub(super) struct ProtocolConsole {
+    pub(super) open: bool,
+    input: String,
+    /// Previously sent messages, oldest first
+    history: Vec<String>,
+    /// Position while browsing history with the arrow keys
+    history_index: Option<usize>,
+    transcript: VecDeque<Traffic>,
+}
+
+impl ProtocolConsole {
+    /// Add traffic reported by the turntable driver.
+    pub(super) fn record(&mut self, traffic: Traffic) {
+        self.transcript.push_back(traffic);
+        if self.transcript.len() > MAX_TRANSCRIPT_LEN {
+            self.transcript.pop_front();
+        }
+    }
+
+    /// Show the console window, returning any command to send to the turntable worker.
+    pub(super) fn window(
+        &mut self,
+        ctx: &egui::Context,
+        worker_state: &TurntableWorkerState,
+    ) -> Option<TurntableWorkerCommand> {
+        let mut command = None;
+        let mut open = self.open;
+        egui::Window::new("Protocol Console")
+            .open(&mut open)
+            .default_size([480.0, 360.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Save transcript...").clicked() {
+                        save_transcript(self.transcript_text());
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.transcript.clear();
+                    }
+                });
+                ui.separator();
+
+                let input_height = 32.0;
+                egui::ScrollArea::vertical()
+                    .max_height(ui.available_height() - input_height)
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for traffic in &self.transcript {
+                            let line = transcript_line(traffic);
+                            match traffic.kind {
+                                TrafficKind::Error => {
+                                    ui.colored_label(ui.visuals().error_fg_color, line)
+                                }
+                                _ => ui.monospace(line),
+                            };
+                        }
+                    });
+
+                ui.separator();
+                let connected = matches!(worker_state, TurntableWorkerState::Connected);
+                ui.add_enabled_ui(connected, |ui| {
+                    ui.horizontal(|ui| {
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.input)
+                                .font(egui::TextStyle::Monospace)
+                                .hint_text("+CT,TURNANGLE=10;")
+                                .desired_width(ui.available_width() - 60.0),
+                        );
+                        if response.has_focus() {
+                            self.browse_history(ui);
+                        }
+                        let entered =
+                            response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                        if (ui.button("Send").clicked() || entered) && !self.input.is_empty() {
+                            let message = std::mem::take(&mut self.input);
+                            if self.history.last() != Some(&message) {
+                                self.history.push(message.clone());
+                            }
+                            self.history_index = None;
+                            command = Some(TurntableWorkerCommand::SendRaw { message });
+                            response.request_focus();
+                        }
+                    });
+                });
+            });
+        self.open = open;
+        command
+    }
+
+    /// Step through previously sent messages with the up and down arrow keys.
+    fn browse_history(&mut self, ui: &egui::Ui) {
+        let (up, down) = ui.input(|i| (i.key_pressed(Key::ArrowUp), i.key_pressed(Key::ArrowDown)));
+        let index = match (up, down, self.history_index) {
+            (true, _, None) => self.history.len().checked_sub(1),
+            (true, _, Some(index)) => Some(index.saturating_sub(1)),
+            (_, true, Some(index)) if index + 1 < self.history.len() => Some(index + 1),
+            (_, true, Some(_)) => None,
+            _ => return,
+        };
+        self.history_index = index;
+        self.input = match index {
+            Some(index) => self.history[index].clone(),
+            None => String::new(),
+        };
+    }
+
+    fn transcript_text(&self) -> String {
+        self.transcript
+            .iter()
+            .map(|traffic| transcript_line(traffic) + "\n")
+            .collect()
+    }
+}
+
+/// Format a traffic entry as `HH:MM:SS.mmm > message`, with the time in UTC.
+fn transcript_line(traffic: &Traffic) -> String {
+    let since_epoch = traffic.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() % (24 * 60 * 60);
+    let marker = match traffic.kind {
+        TrafficKind::Sent => ">",
+        TrafficKind::Received => "<",
+        TrafficKind::Error => "!",
+    };
+    format!(
+        "{:02}:{:02}:{:02}.{:03} {} {}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        since_epoch.subsec_millis(),
+        marker,
+        traffic.message
+    )
+}
+
+/// Ask where to save the transcript, and write it there. Runs outside the UI thread, as the dialog blocks.
+fn save_transcript(text: String) {
+    std::thread::spawn(move || {
+        let dialog = FileDialog::new()
+            .set_title("Save Transcript")
+            .set_file_name(format!(
+                "turntable-transcript-{}.txt",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            ));
+        if let Some(path) = dialog.save_file() {
+            if let Err(e) = std::fs::write(&path, text) {
+                eprintln!("Couldn't save transcript to {:?}: {:?}", path, e);
+            }
+        }
+    });
+}
@@ -0,0 +1,120 @@
+//! Turntable driver selection and per-driver settings UI.
+
+use eframe::egui::{self, DragValue};
+
+use crate::turntable::DriverSettings;
+
+/// Show a dropdown of available drivers, followed by the settings for the selected driver.
+pub(super) fn driver_settings_ui(ui: &mut egui::Ui, settings: &mut DriverSettings) {
+    egui::ComboBox::from_label("Driver")
+        .selected_text(settings.name())
+        .show_ui(ui, |ui| {
+            for driver in DriverSettings::registry() {
+                let selected = driver.name() == settings.name();
+                // Keep the current settings if the same driver is picked again
+                if ui.selectable_label(selected, driver.name()).clicked() && !selected {
+                    *settings = driver;
+                }
+            }
+        });
+
+    match settings {
+        DriverSettings::RevopointBle => {}
+        DriverSettings::RevopointSerial { port, baud_rate } => {
+            port_ui(ui, port);
+            labelled(ui, "Baud rate:", DragValue::new(baud_rate));
+        }
+        DriverSettings::Grbl { port, config } => {
+            port_ui(ui, port);
+            labelled(ui, "Baud rate:", DragValue::new(&mut config.baud_rate));
+            axis_ui(ui, "Rotation axis", &mut config.rotation_axis);
+            axis_ui(ui, "Tilt axis", &mut config.tilt_axis);
+            labelled(
+                ui,
+                "Rotation steps/deg:",
+                DragValue::new(&mut config.rotation_steps_per_degree)
+                    .speed(0.1)
+                    .range(0.001..=f32::MAX),
+            );
+            labelled(
+                ui,
+                "Tilt steps/deg:",
+                DragValue::new(&mut config.tilt_steps_per_degree)
+                    .speed(0.1)
+                    .range(0.001..=f32::MAX),
+            );
+            labelled(
+                ui,
+                "Max rotation feed:",
+                DragValue::new(&mut config.rotation_feed_rate)
+                    .range(1.0..=f32::MAX)
+                    .suffix(" deg/min"),
+            );
+            labelled(
+                ui,
+                "Max tilt feed:",
+                DragValue::new(&mut config.tilt_feed_rate)
+                    .range(1.0..=f32::MAX)
+                    .suffix(" deg/min"),
+            );
+            labelled(
+                ui,
+                "Max tilt:",
+                DragValue::new(&mut config.max_tilt_deg)
+                    .range(1.0..=180.0)
+                    .suffix(" deg"),
+            );
+            ui.checkbox(&mut config.homing, "Home ($H) on connect and reset");
+            ui.add_enabled_ui(config.homing, |ui| {
+                labelled(
+                    ui,
+                    "Tilt at home:",
+                    DragValue::new(&mut config.tilt_home_deg).suffix(" deg"),
+                );
+            });
+        }
+        DriverSettings::Simulated {
+            rotation_speed,
+            tilt_speed,
+        } => {
+            labelled(
+                ui,
+                "Max rotation speed:",
+                DragValue::new(rotation_speed)
+                    .range(1.0..=360.0)
+                    .suffix(" deg/s"),
+            );
+            labelled(
+                ui,
+                "Max tilt speed:",
+                DragValue::new(tilt_speed)
+                    .range(1.0..=90.0)
+                    .suffix(" deg/s"),
+            );
+        }
+    }
+}
+
+fn labelled(ui: &mut egui::Ui, label: &str, widget: impl egui::Widget) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(widget);
+    });
+}
+
+fn port_ui(ui: &mut egui::Ui, port: &mut String) {
+    ui.horizontal(|ui| {
+        ui.label("Port:");
+        ui.add(egui::TextEdit::singleline(port).hint_text("first available"));
+    });
+}
+
+fn axis_ui(ui: &mut egui::Ui, label: &str, axis: &mut char) {
+    egui::ComboBox::from_label(label)
+        .selected_text(axis.to_string())
+        .show_ui(ui, |ui| {
+            for option in ['X', 'Y', 'Z'] {
+                ui.selectable_value(axis, option, option.to_string());
+            }
+        });
+}
@@ -0,0 +1,114 @@
+//! Controls for capturing several frames at each pose, as an exposure bracket or focus stack.
+
+use eframe::egui::{self, DragValue};
+
+use crate::camera::{BracketMethod, ExposureBracket, FocusStack, FrameSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Single,
+    Bracket,
+    FocusStack,
+}
+
+/// Frame set UI state. Settings for each mode are kept when switching between them.
+pub(super) struct FrameSetControls {
+    mode: Mode,
+    bracket_frames: u8,
+    bracket_step_stops: f32,
+    bracket_method: BracketMethod,
+    focus_steps: u16,
+    focus_drive: i32,
+    /// Merge each set into one image when exporting
+    pub(super) merge: bool,
+}
+
+impl Default for FrameSetControls {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Single,
+            bracket_frames: 3,
+            bracket_step_stops: 1.0,
+            bracket_method: BracketMethod::ConfigChange,
+            focus_steps: 8,
+            focus_drive: 2,
+            merge: true,
+        }
+    }
+}
+
+impl FrameSetControls {
+    /// The frames to capture at each pose, unless capturing a single image.
+    pub(super) fn frame_set(&self) -> Option<FrameSet> {
+        match self.mode {
+            Mode::Single => None,
+            Mode::Bracket => Some(FrameSet::Bracket(ExposureBracket::centred(
+                self.bracket_frames,
+                self.bracket_step_stops,
+                self.bracket_method,
+            ))),
+            Mode::FocusStack => Some(FrameSet::FocusStack(FocusStack {
+                steps: self.focus_steps,
+                drive: self.focus_drive,
+            })),
+        }
+    }
+
+    pub(super) fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.mode, Mode::Single, "Single");
+            ui.radio_value(&mut self.mode, Mode::Bracket, "Exposure bracket");
+            ui.radio_value(&mut self.mode, Mode::FocusStack, "Focus stack");
+        });
+        match self.mode {
+            Mode::Single => return,
+            Mode::Bracket => self.bracket_ui(ui),
+            Mode::FocusStack => self.focus_stack_ui(ui),
+        }
+        ui.checkbox(&mut self.merge, "Merge frames when exporting");
+    }
+
+    fn bracket_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.bracket_frames)
+                    .range(2..=9)
+                    .suffix(" frames"),
+            );
+            ui.label("at");
+            ui.add(
+                DragValue::new(&mut self.bracket_step_stops)
+                    .range(0.3..=3.0)
+                    .speed(0.1)
+                    .max_decimals(1)
+                    .suffix(" EV"),
+            );
+            ui.label("apart");
+        });
+        ui.radio_value(
+            &mut self.bracket_method,
+            BracketMethod::ConfigChange,
+            "Set exposure compensation per frame",
+        );
+        ui.radio_value(
+            &mut self.bracket_method,
+            BracketMethod::CameraAeb,
+            "Use the camera's AEB (set up on the body)",
+        );
+    }
+
+    fn focus_stack_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.focus_steps)
+                    .range(2..=50)
+                    .suffix(" frames"),
+            );
+            ui.label("driving focus by");
+            ui.add(DragValue::new(&mut self.focus_drive).range(1..=1000));
+        });
+        ui.label(
+            "Focus on the nearest part of the object first. Canon bodies take drives of 1 to 3.",
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Gallery view showing images in capture order, a thumbnail per pose and camera.
+
+use eframe::egui::{self, load::SizedTexture, ImageSource};
+
+use super::inspector::{self, ImageKey};
+use super::{representative, ImagePreview};
+use crate::app::worker::{CameraId, TurntableSteppingState};
+use crate::camera::FrameSetting;
+
+const COLUMNS: usize = 4;
+const SPACING: f32 = 10.0;
+
+/// Show a thumbnail for each pose and camera with any warnings about it, flagging poses where the
+/// table didn't move in `last_job`. Returns the image clicked, if any.
+pub(super) fn gallery_grid_ui(
+    ui: &mut egui::Ui,
+    images: &[ImagePreview],
+    last_job: Option<&TurntableSteppingState>,
+    selected: Option<(Option<u32>, u32, CameraId)>,
+) -> Option<ImageKey> {
+    let mut clicked = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        let img_width = ui.available_width() / (COLUMNS as f32) - SPACING;
+
+        egui::Grid::new("image_grid")
+            .num_columns(COLUMNS)
+            .spacing([SPACING, SPACING])
+            .show(ui, |ui| {
+                let groups = images.chunk_by(|a, b| a.pose_key() == b.pose_key());
+                for (i, group) in groups.enumerate() {
+                    let image = representative(group);
+                    let Some(texture) = &image.texture else {
+                        continue;
+                    };
+                    ui.vertical(|ui| {
+                        let key = image.pose_key();
+                        let response = ui.add(
+                            egui::Image::new(ImageSource::Texture(SizedTexture::from_handle(
+                                texture,
+                            )))
+                            .max_width(img_width)
+                            .sense(egui::Sense::click()),
+                        );
+                        if response.clicked() {
+                            clicked = Some(inspector::key(image));
+                        }
+                        if selected == Some(key) {
+                            ui.painter().rect_stroke(
+                                response.rect,
+                                2.0,
+                                ui.visuals().selection.stroke,
+                                egui::StrokeKind::Outside,
+                            );
+                        }
+                        match group[0].setting {
+                            Some(FrameSetting::ExposureStops(_)) => {
+                                ui.label(format!("{} exposures", group.len()));
+                            }
+                            Some(FrameSetting::FocusStep(_)) => {
+                                ui.label(format!("{} focus steps", group.len()));
+                            }
+                            None => {}
+                        }
+                        if group.iter().any(|img| img.blurred) {
+                            ui.colored_label(ui.visuals().error_fg_color, "Possibly blurred");
+                        }
+                        let unmoved = last_job.is_some_and(|job| {
+                            image.job == Some(job.id()) && job.unmoved_steps().contains(&image.seq)
+                        });
+                        if unmoved {
+                            ui.colored_label(ui.visuals().error_fg_color, "Table didn't move");
+                        }
+                    });
+                    if (i + 1) % COLUMNS == 0 {
+                        ui.end_row();
+                    }
+                }
+            })
+    });
+    clicked
+}
@@ -0,0 +1,273 @@
+//! Full resolution viewer for checking focus, with the camera's exposure settings and the pose.
+
+use std::path::PathBuf;
+
+use eframe::egui::{self, ColorImage, Key, TextureHandle, TextureOptions, Vec2};
+use image::{imageops, RgbaImage};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use super::ImagePreview;
+use crate::app::worker::{self, CameraId};
+use crate::camera::{self, Exif, FrameSetting};
+
+/// Identifies an image in the gallery by job, pose, camera and shot.
+pub(super) type ImageKey = (Option<u32>, u32, CameraId, u16);
+
+pub(super) fn key(image: &ImagePreview) -> ImageKey {
+    let (job, seq, camera) = image.pose_key();
+    (job, seq, camera, image.shot)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Zoom {
+    /// Whole image in the window
+    Fit,
+    /// Image pixels per screen pixel
+    Scale(f32),
+}
+
+/// An image decoded in the background.
+struct Decoded {
+    key: ImageKey,
+    /// Shrunk if it's larger than the biggest texture the GPU takes
+    image: anyhow::Result<RgbaImage>,
+    /// Full size, before any shrinking
+    size: [u32; 2],
+    exif: anyhow::Result<Exif>,
+}
+
+struct Loaded {
+    key: ImageKey,
+    image: Result<TextureHandle, String>,
+    size: [u32; 2],
+    exif: Result<Exif, String>,
+}
+
+/// Image inspector UI state.
+pub(super) struct ImageInspector {
+    /// Image being inspected, while the window is open
+    shown: Option<ImageKey>,
+    zoom: Zoom,
+    loaded: Option<Loaded>,
+    /// Image being decoded, so requests aren't repeated every frame
+    loading: Option<ImageKey>,
+    decoded_tx: UnboundedSender<Decoded>,
+    decoded_rx: UnboundedReceiver<Decoded>,
+}
+
+impl Default for ImageInspector {
+    fn default() -> Self {
+        let (decoded_tx, decoded_rx) = mpsc::unbounded_channel();
+        Self {
+            shown: None,
+            zoom: Zoom::Fit,
+            loaded: None,
+            loading: None,
+            decoded_tx,
+            decoded_rx,
+        }
+    }
+}
+
+impl ImageInspector {
+    /// Open the window on an image.
+    pub(super) fn open(&mut self, image: ImageKey) {
+        self.shown = Some(image);
+    }
+
+    /// Decode an image on another thread, as full size JPEGs take a while.
+    fn load(&mut self, ctx: &egui::Context, key: ImageKey, path: PathBuf) {
+        self.loading = Some(key);
+        let max_side = ctx.input(|i| i.max_texture_side) as u32;
+        let decoded_tx = self.decoded_tx.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let mut size = [0, 0];
+            let image = worker::load_image(&path).map(|image| {
+                size = [image.width(), image.height()];
+                let scale = max_side as f32 / image.width().max(image.height()) as f32;
+                match scale < 1.0 {
+                    true => imageops::resize(
+                        &image,
+                        (image.width() as f32 * scale) as u32,
+                        (image.height() as f32 * scale) as u32,
+                        imageops::FilterType::Triangle,
+                    ),
+                    false => image,
+                }
+            });
+            let exif = camera::read_exif(&path);
+            let _ = decoded_tx.send(Decoded {
+                key,
+                image,
+                size,
+                exif,
+            });
+            ctx.request_repaint();
+        });
+    }
+
+    fn receive(&mut self, ctx: &egui::Context) {
+        while let Ok(decoded) = self.decoded_rx.try_recv() {
+            if Some(decoded.key) != self.shown {
+                continue;
+            }
+            self.loading = None;
+            let image = decoded.image.map(|image| {
+                let color_image = ColorImage::from_rgba_unmultiplied(
+                    [image.width() as usize, image.height() as usize],
+                    image.as_raw(),
+                );
+                ctx.load_texture("inspected_image", color_image, TextureOptions::LINEAR)
+            });
+            self.loaded = Some(Loaded {
+                key: decoded.key,
+                image: image.map_err(|e| e.to_string()),
+                size: decoded.size,
+                exif: decoded.exif.map_err(|e| e.to_string()),
+            });
+        }
+    }
+
+    /// Show the inspector window, if it's open. `images` are the gallery's, in order.
+    pub(super) fn window(&mut self, ctx: &egui::Context, images: &[ImagePreview]) {
+        self.receive(ctx);
+        let Some(index) = self
+            .shown
+            .and_then(|shown| images.iter().position(|image| key(image) == shown))
+        else {
+            self.shown = None;
+            self.loaded = None;
+            return;
+        };
+        let image = &images[index];
+        let is_loaded = self
+            .loaded
+            .as_ref()
+            .is_some_and(|loaded| loaded.key == key(image));
+        if !is_loaded && self.loading != self.shown {
+            self.load(ctx, key(image), image.path.clone());
+        }
+        let loaded = self
+            .loaded
+            .as_ref()
+            .filter(|loaded| loaded.key == key(image));
+        let zoom = &mut self.zoom;
+
+        let mut open = true;
+        let mut step: isize = 0;
+        egui::Window::new("Image")
+            .open(&mut open)
+            .default_size([900.0, 700.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(index > 0, egui::Button::new("< Previous"))
+                        .clicked()
+                    {
+                        step = -1;
+                    }
+                    if ui
+                        .add_enabled(index + 1 < images.len(), egui::Button::new("Next >"))
+                        .clicked()
+                    {
+                        step = 1;
+                    }
+                    ui.label(format!("{} of {}", index + 1, images.len()));
+                    ui.separator();
+                    ui.radio_value(zoom, Zoom::Fit, "Fit");
+                    ui.radio_value(zoom, Zoom::Scale(1.0), "100%");
+                    ui.radio_value(zoom, Zoom::Scale(2.0), "200%");
+                });
+                ui.label(describe_image(image));
+                match loaded {
+                    Some(loaded) => {
+                        match &loaded.exif {
+                            Ok(exif) => ui.label(describe_exif(exif)),
+                            Err(e) => ui.label(format!("No exposure details: {}", e)),
+                        };
+                        ui.separator();
+                        match &loaded.image {
+                            Ok(texture) => image_ui(ui, *zoom, texture, loaded.size),
+                            Err(e) => {
+                                ui.colored_label(ui.visuals().error_fg_color, e);
+                            }
+                        }
+                    }
+                    None => {
+                        ui.separator();
+                        ui.spinner();
+                    }
+                }
+            });
+        if !ctx.wants_keyboard_input() {
+            ctx.input(|i| {
+                if i.key_pressed(Key::ArrowLeft) && index > 0 {
+                    step = -1;
+                }
+                if i.key_pressed(Key::ArrowRight) && index + 1 < images.len() {
+                    step = 1;
+                }
+            });
+        }
+        if step != 0 {
+            self.shown = Some(key(&images[index.saturating_add_signed(step)]));
+        }
+        if !open {
+            self.shown = None;
+            self.loaded = None;
+        }
+    }
+}
+
+/// The image at the chosen zoom. Zoomed in, it pans by dragging or scrolling.
+fn image_ui(ui: &mut egui::Ui, zoom: Zoom, texture: &TextureHandle, size: [u32; 2]) {
+    match zoom {
+        Zoom::Fit => {
+            ui.add(egui::Image::new(texture).max_size(ui.available_size()));
+        }
+        Zoom::Scale(scale) => {
+            // Sized in screen pixels, whatever the texture was shrunk to
+            let points =
+                Vec2::new(size[0] as f32, size[1] as f32) * scale / ui.ctx().pixels_per_point();
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.add(egui::Image::new(texture).fit_to_exact_size(points));
+            });
+        }
+    }
+}
+
+/// Which pose, camera and frame an image is, and where the table was.
+fn describe_image(image: &ImagePreview) -> String {
+    let frame = match image.setting {
+        Some(FrameSetting::ExposureStops(stops)) => format!(", {:+.1} EV", stops),
+        Some(FrameSetting::FocusStep(step)) => format!(", focus step {}", step),
+        None => String::new(),
+    };
+    let pose = match image.pose {
+        Some(pose) => format!(
+            "rotation {:.1} deg, tilt {:+.1} deg",
+            pose.rotation_deg.rem_euclid(360.0),
+            pose.tilt_deg
+        ),
+        None => "captured outside a job".to_string(),
+    };
+    format!("Pose {} on {}{}: {}", image.seq, image.camera, frame, pose)
+}
+
+fn describe_exif(exif: &Exif) -> String {
+    let details: Vec<String> = [
+        exif.shutter(),
+        exif.f_number.map(|f| format!("f/{:.1}", f)),
+        exif.iso.map(|iso| format!("ISO {}", iso)),
+        exif.focal_length.map(|mm| format!("{:.0} mm", mm)),
+        exif.timestamp.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    match details.is_empty() {
+        true => "No exposure details recorded".to_string(),
+        false => details.join("   "),
+    }
+}
@@ -0,0 +1,57 @@
+//! Elapsed time, ETA and per-phase timings for the current or last job.
+
+use std::time::Duration;
+
+use eframe::egui;
+
+use crate::app::worker::{Phase, TurntableSteppingState};
+
+/// Format as `m:ss`, or `h:mm:ss` past an hour.
+pub(super) fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    }
+}
+
+/// Show timing statistics for a job. `running` is false once the job has finished or stopped.
+pub(super) fn job_stats_ui(ui: &mut egui::Ui, job: &TurntableSteppingState, running: bool) {
+    let timing = job.timing();
+    ui.label(match (running, timing.remaining(job.total_steps())) {
+        (true, Some(remaining)) => format!(
+            "Elapsed {}, about {} remaining",
+            format_duration(timing.elapsed()),
+            format_duration(remaining)
+        ),
+        (true, None) => format!("Elapsed {}", format_duration(timing.elapsed())),
+        (false, _) => format!(
+            "Last job: {} of {} poses captured",
+            timing.completed,
+            job.total_steps()
+        ),
+    });
+
+    let last = timing
+        .completed
+        .checked_sub(1)
+        .and_then(|i| timing.poses.get(i));
+    let (Some(mean), Some(last)) = (timing.mean(), last) else {
+        return;
+    };
+    egui::Grid::new("job_stats")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            ui.label("Last");
+            ui.label("Average");
+            ui.end_row();
+            for phase in Phase::ALL {
+                ui.label(phase.name());
+                ui.label(format!("{:.2} s", last.phase(phase).as_secs_f32()));
+                ui.label(format!("{:.2} s", mean.phase(phase).as_secs_f32()));
+                ui.end_row();
+            }
+        });
+}
@@ -0,0 +1,94 @@
+//! Manual jog controls, for checking framing without running a job.
+
+use eframe::egui::{self, DragValue};
+
+use crate::app::worker::{TurntableWorkerCommand, TurntableWorkerState};
+use crate::turntable::Pose;
+
+/// Relative rotations offered as buttons, in degrees.
+const JOG_STEPS_DEG: [f32; 4] = [1.0, 5.0, 15.0, 90.0];
+
+/// Manual jog UI state.
+#[derive(Default)]
+pub(super) struct JogControls {
+    /// Tilt angle to move to
+    tilt_deg: f32,
+    /// Direction of the continuous rotation button being held, if any
+    held_direction: Option<i8>,
+}
+
+impl JogControls {
+    /// Show the jog controls and estimated pose, returning any command to send to the turntable worker.
+    /// `max_tilt_deg` is the driver's tilt limit either side of level.
+    pub(super) fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        worker_state: &TurntableWorkerState,
+        pose: Option<Pose>,
+        max_tilt_deg: f32,
+    ) -> Option<TurntableWorkerCommand> {
+        let mut command = None;
+        let connected = matches!(worker_state, TurntableWorkerState::Connected);
+
+        ui.label(match (worker_state, pose) {
+            (TurntableWorkerState::RotatingContinuously { direction }, _) => {
+                format!(
+                    "Rotating {}...",
+                    if *direction < 0 { "left" } else { "right" }
+                )
+            }
+            (_, Some(pose)) => format!(
+                "Rotation: {:.1} deg, Tilt: {:+.1} deg",
+                pose.rotation_deg.rem_euclid(360.0),
+                pose.tilt_deg
+            ),
+            (_, None) => "Position unknown, reset position to jog".to_string(),
+        });
+
+        ui.add_enabled_ui(connected && pose.is_some(), |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for degrees in JOG_STEPS_DEG.iter().rev().map(|step| -step) {
+                    if ui.button(format!("{:+}", degrees)).clicked() {
+                        command = Some(TurntableWorkerCommand::JogRotation { degrees });
+                    }
+                }
+                for degrees in JOG_STEPS_DEG {
+                    if ui.button(format!("{:+}", degrees)).clicked() {
+                        command = Some(TurntableWorkerCommand::JogRotation { degrees });
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(
+                    DragValue::new(&mut self.tilt_deg)
+                        .range(-max_tilt_deg..=max_tilt_deg)
+                        .speed(0.5)
+                        .suffix(" deg"),
+                );
+                if ui.button("Tilt").clicked() {
+                    command = Some(TurntableWorkerCommand::JogTilt {
+                        tilt_deg: self.tilt_deg,
+                    });
+                }
+            });
+        });
+
+        ui.horizontal(|ui| {
+            // Rotate for as long as the button is held down
+            for (label, direction) in [("◀ Hold", -1), ("Hold ▶", 1)] {
+                let holding = self.held_direction == Some(direction);
+                let response = ui.add_enabled(connected || holding, egui::Button::new(label));
+                let down = response.is_pointer_button_down_on();
+                if down && connected && self.held_direction.is_none() {
+                    self.held_direction = Some(direction);
+                    command = Some(TurntableWorkerCommand::StartContinuousRotation { direction });
+                } else if !down && holding {
+                    self.held_direction = None;
+                    command = Some(TurntableWorkerCommand::StopContinuousRotation);
+                }
+            }
+        });
+
+        command
+    }
+}
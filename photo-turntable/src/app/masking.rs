@@ -0,0 +1,208 @@
+//! Controls for exporting object masks, from background plates or by keying out a coloured backdrop.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use image::RgbaImage;
+
+use crate::app::worker::{self, MaskJob, MaskMethod, MaskNaming};
+use crate::mask::{ChromaKey, MaskOutput, MaskSettings};
+
+/// Thumbnails are decoded at an eighth of full size, so the preview's feathering is scaled to match.
+const PREVIEW_SCALE: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Source {
+    Plate,
+    ChromaKey,
+}
+
+/// Keyed thumbnail of the selected image, rebuilt when the image or the key changes.
+struct KeyPreview {
+    path: PathBuf,
+    thumbnail: RgbaImage,
+    key: ChromaKey,
+    texture: TextureHandle,
+}
+
+/// Mask UI state. Settings for each source are kept when switching between them.
+pub(super) struct MaskControls {
+    /// Write a mask or cutout alongside each image when exporting
+    export: bool,
+    source: Source,
+    plate_settings: MaskSettings,
+    chroma_key: ChromaKey,
+    output: MaskOutput,
+    naming: MaskNaming,
+    preview: Option<KeyPreview>,
+}
+
+impl Default for MaskControls {
+    fn default() -> Self {
+        Self {
+            export: false,
+            source: Source::Plate,
+            plate_settings: MaskSettings::default(),
+            chroma_key: ChromaKey::default(),
+            output: MaskOutput::default(),
+            naming: MaskNaming::default(),
+            preview: None,
+        }
+    }
+}
+
+impl MaskControls {
+    /// Masks to export for a pose, given the frames of the background plate matching it, if any.
+    pub(super) fn mask_job(&self, plate: Option<Vec<PathBuf>>) -> Option<MaskJob> {
+        if !self.export {
+            return None;
+        }
+        let method = match self.source {
+            Source::Plate => MaskMethod::Plate {
+                plates: plate?,
+                settings: self.plate_settings,
+            },
+            Source::ChromaKey => MaskMethod::Chroma(self.chroma_key),
+        };
+        Some(MaskJob {
+            method,
+            output: self.output,
+            naming: self.naming,
+        })
+    }
+
+    /// Returns true if background plates should be captured. `selected` is the image to preview the
+    /// chroma key on.
+    pub(super) fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        plates: usize,
+        can_capture_background: bool,
+        selected: Option<&Path>,
+    ) -> bool {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.source, Source::Plate, "Background plate");
+            ui.radio_value(&mut self.source, Source::ChromaKey, "Chroma key");
+        });
+        let capture_background = match self.source {
+            Source::Plate => self.plate_ui(ui, plates, can_capture_background),
+            Source::ChromaKey => {
+                self.chroma_key_ui(ui, selected);
+                false
+            }
+        };
+        ui.checkbox(&mut self.export, "Export with the images");
+        ui.add_enabled_ui(self.export, |ui| {
+            ui.horizontal(|ui| {
+                for output in MaskOutput::ALL {
+                    ui.radio_value(&mut self.output, output, output.name());
+                }
+            });
+            ui.add_enabled_ui(self.output == MaskOutput::Mask, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Named for:");
+                    for naming in MaskNaming::ALL {
+                        ui.radio_value(&mut self.naming, naming, naming.name());
+                    }
+                });
+            });
+        });
+        capture_background
+    }
+
+    fn plate_ui(&mut self, ui: &mut egui::Ui, plates: usize, can_capture: bool) -> bool {
+        ui.label("Capture the empty table at each tilt ring before placing the object.");
+        let capture = ui
+            .horizontal(|ui| {
+                let clicked = ui
+                    .add_enabled(can_capture, egui::Button::new("Capture Background"))
+                    .clicked();
+                ui.label(format!("{} plates", plates));
+                clicked
+            })
+            .inner;
+        ui.add(
+            egui::Slider::new(&mut self.plate_settings.threshold, 0.02..=0.5)
+                .text("difference threshold"),
+        );
+        ui.add(
+            egui::Slider::new(&mut self.plate_settings.cleanup_radius, 0..=20)
+                .text("cleanup radius")
+                .suffix(" px"),
+        );
+        capture
+    }
+
+    fn chroma_key_ui(&mut self, ui: &mut egui::Ui, selected: Option<&Path>) {
+        ui.horizontal(|ui| {
+            ui.label("Backdrop colour:");
+            egui::color_picker::color_edit_button_srgb(ui, &mut self.chroma_key.color);
+        });
+        ui.add(egui::Slider::new(&mut self.chroma_key.tolerance, 0.01..=0.5).text("tolerance"));
+        ui.add(egui::Slider::new(&mut self.chroma_key.spill, 0.0..=1.0).text("spill suppression"));
+        ui.add(
+            egui::Slider::new(&mut self.chroma_key.feather_radius, 0..=20)
+                .text("edge feather")
+                .suffix(" px"),
+        );
+
+        let Some(path) = selected else {
+            ui.label("Select an image in the gallery to preview the key.");
+            return;
+        };
+        if let Err(e) = self.update_preview(ui.ctx(), path) {
+            eprintln!("Couldn't preview chroma key on {:?}: {:?}", path, e);
+            self.preview = None;
+        }
+        let Some(preview) = &self.preview else {
+            return;
+        };
+        let response = ui.add(
+            egui::Image::new(&preview.texture)
+                .max_width(ui.available_width())
+                .sense(egui::Sense::click()),
+        );
+        // Clicking the backdrop picks its colour
+        if let Some(position) = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+        {
+            let relative = (position - response.rect.min) / response.rect.size();
+            let (width, height) = preview.thumbnail.dimensions();
+            let x = ((relative.x * width as f32) as u32).min(width - 1);
+            let y = ((relative.y * height as f32) as u32).min(height - 1);
+            let [r, g, b, _] = preview.thumbnail.get_pixel(x, y).0;
+            self.chroma_key.color = [r, g, b];
+        }
+        ui.label("Click the backdrop to pick its colour.");
+    }
+
+    fn update_preview(&mut self, ctx: &egui::Context, path: &Path) -> anyhow::Result<()> {
+        let thumbnail = match self.preview.take() {
+            Some(preview) if preview.path == path => {
+                if preview.key == self.chroma_key {
+                    self.preview = Some(preview);
+                    return Ok(());
+                }
+                preview.thumbnail
+            }
+            _ => worker::load_thumbnail(path)?,
+        };
+        let key = ChromaKey {
+            feather_radius: self.chroma_key.feather_radius.div_ceil(PREVIEW_SCALE),
+            ..self.chroma_key
+        };
+        let cutout = key.cut_out(&image::DynamicImage::ImageRgba8(thumbnail.clone()).to_rgb8());
+        let image = ColorImage::from_rgba_unmultiplied(
+            [cutout.width() as usize, cutout.height() as usize],
+            cutout.as_raw(),
+        );
+        self.preview = Some(KeyPreview {
+            path: path.to_path_buf(),
+            thumbnail,
+            key: self.chroma_key,
+            texture: ctx.load_texture("chroma_key_preview", image, TextureOptions::default()),
+        });
+        Ok(())
+    }
+}
@@ -1,72 +1,92 @@
+mod calibration;
+mod console;
+mod driver_settings;
+mod frame_set;
+mod gallery_grid;
+mod inspector;
+mod job_stats;
+mod jog;
+mod masking;
+mod pose_matrix;
+mod rotation_range;
+mod settle;
 mod worker;
 
+use std::collections::BTreeMap;
 use std::ops::Deref;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use self::worker::{TurntableWorker, TurntableWorkerCommand, TurntableWorkerState};
 use crate::app::worker::{
-    CameraWorker, CameraWorkerCommand, CameraWorkerState, ExportJob, TurntableSteppingJob,
+    CalibrationProgress, CameraId, CameraTrigger, CameraWorker, CameraWorkerCommand,
+    CameraWorkerState, CaptureTimeouts, CompletionDetection, ConnectedCamera, ExportFrame,
+    ExportJob, ImageHandle, JobTiming, MotionCheck, PoseOrdering, TurntableSteppingJob,
+    TurntableSteppingState,
 };
-use crate::camera::CameraSpec;
-use crate::turntable::Turntable;
+use crate::camera::{CameraSpec, FrameSetting};
+use crate::quality;
+use crate::turntable::{DriverSettings, MotionSpeeds, Pose, Traffic, TrafficLog};
 
-use eframe::egui::load::SizedTexture;
-use eframe::egui::{
-    Color32, ColorImage, Context, ImageSource, Layout, Stroke, TextureHandle, Vec2,
-};
+use eframe::egui::{Color32, ColorImage, Context, DragValue, Layout, Stroke, TextureHandle, Vec2};
 use eframe::emath::Align;
 use eframe::{egui, App, CreationContext, Frame};
 use rfd::FileDialog;
 use tokio::runtime::Runtime;
-use tokio::sync::broadcast;
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{broadcast, watch, Notify};
 
 use anyhow::anyhow;
 
 struct ImagePreview {
+    /// Job that captured the image, if any
+    job: Option<u32>,
     seq: u32,
+    camera: CameraId,
+    /// Index among the frames captured at the same pose
+    shot: u16,
+    setting: Option<FrameSetting>,
+    /// Relative to where the job started, if captured by a job
+    pose: Option<Pose>,
+    background: bool,
     path: PathBuf,
+    sharpness: f32,
+    /// Much less sharp than similar images in the session
+    blurred: bool,
     thumb: Option<ColorImage>,
     texture: Option<TextureHandle>,
 }
 
 impl ImagePreview {
     /// Load and resize image, returning egui texture
-    fn load(seq: u32, path: &Path) -> anyhow::Result<Self> {
-        let jpeg_data = std::fs::read(path)?;
-
-        // initialize a decompressor with the scaling factor
-        let mut decompressor = turbojpeg::Decompressor::new()?;
-        let scaling = turbojpeg::ScalingFactor::ONE_EIGHTH;
-        decompressor.set_scaling_factor(scaling)?;
-
-        // read the JPEG header and downscale the width and height
-        let scaled_header = decompressor.read_header(&jpeg_data)?.scaled(scaling);
-
-        // initialize the image (Image<Vec<u8>>)
-        let mut image = turbojpeg::Image {
-            pixels: vec![0; 4 * scaled_header.width * scaled_header.height],
-            width: scaled_header.width,
-            pitch: 4 * scaled_header.width, // size of one image row in memory
-            height: scaled_header.height,
-            format: turbojpeg::PixelFormat::RGBA,
-        };
-
-        // decompress the JPEG into the image
-        // (we use as_deref_mut() to convert from &mut Image<Vec<u8>> into Image<&mut [u8]>)
-        decompressor.decompress(&jpeg_data, image.as_deref_mut())?;
-
-        let color_image =
-            ColorImage::from_rgba_unmultiplied([image.width, image.height], &image.pixels);
+    fn load(handle: &ImageHandle) -> anyhow::Result<Self> {
+        let thumbnail = worker::load_thumbnail(&handle.path)?;
+        let color_image = ColorImage::from_rgba_unmultiplied(
+            [thumbnail.width() as usize, thumbnail.height() as usize],
+            thumbnail.as_raw(),
+        );
         Ok(Self {
-            seq,
-            path: path.to_path_buf(),
+            job: handle.job,
+            seq: handle.seq,
+            camera: handle.camera,
+            shot: handle.shot,
+            setting: handle.setting,
+            pose: handle.pose,
+            background: handle.background,
+            path: handle.path.clone(),
+            sharpness: quality::sharpness(&thumbnail),
+            blurred: false,
             thumb: Some(color_image),
             texture: None,
         })
     }
 
+    /// Identifies the pose and camera an image was captured at, shared by the frames of a set.
+    fn pose_key(&self) -> (Option<u32>, u32, CameraId) {
+        (self.job, self.seq, self.camera)
+    }
+
     fn load_texture<'a>(&mut self, ctx: &Context) -> anyhow::Result<()> {
         let texture_name = self
             .path
@@ -85,60 +105,157 @@ impl ImagePreview {
     }
 }
 
+/// Image to show for a pose: the frame nearest the metered exposure of a bracket,
+/// or the middle of a focus stack.
+fn representative(group: &[ImagePreview]) -> &ImagePreview {
+    match group[0].setting {
+        Some(FrameSetting::ExposureStops(_)) => group
+            .iter()
+            .min_by(|a, b| {
+                let stops = |img: &ImagePreview| match img.setting {
+                    Some(FrameSetting::ExposureStops(stops)) => stops.abs(),
+                    _ => f32::INFINITY,
+                };
+                stops(a).total_cmp(&stops(b))
+            })
+            .unwrap(),
+        _ => &group[group.len() / 2],
+    }
+}
+
+/// How the gallery lays out images.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GalleryView {
+    /// In capture order
+    Grid,
+    /// By tilt ring and rotation position of the last job
+    PoseMatrix,
+}
+
+impl GalleryView {
+    const ALL: [Self; 2] = [Self::Grid, Self::PoseMatrix];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Grid => "Grid",
+            Self::PoseMatrix => "Pose matrix",
+        }
+    }
+}
+
+/// Whole seconds editor for a timeout.
+fn seconds(duration: &mut Duration, max_secs: u64) -> DragValue<'_> {
+    DragValue::from_get_set(move |value| {
+        if let Some(value) = value {
+            *duration = Duration::from_secs(value as u64);
+        }
+        duration.as_secs() as f64
+    })
+    .range(1..=max_secs)
+    .suffix(" s")
+}
+
 /// UI state holding channels and current values
-pub(crate) struct TurntableApp<T: Turntable> {
+pub(crate) struct TurntableApp {
     worker_state: TurntableWorkerState,
+    driver_settings: DriverSettings,
+    calibration_window_open: bool,
+    calibration_detection: CompletionDetection,
+    /// Latest calibration progress, kept after calibration finishes to show the result
+    calibration: Option<CalibrationProgress>,
+    /// Latest state of the current or last job, kept after it finishes to show its timings
+    last_job: Option<TurntableSteppingState>,
+    /// Phase timings of each job by ID, exported alongside its images
+    job_timings: BTreeMap<u32, JobTiming>,
+    jog: jog::JogControls,
+    console: console::ProtocolConsole,
     camera_state: CameraWorkerState,
-    slider_steps: u16,
+    rotation_range: rotation_range::RotationRangeControls,
     tilt_slider_low_deg: i16,
     tilt_slider_high_deg: i16,
     tilt_steps: u16,
-    selected_camera_spec: Option<CameraSpec>,
+    job_speeds: MotionSpeeds,
+    pose_ordering: PoseOrdering,
+    camera_trigger: CameraTrigger,
+    camera_timeouts: CaptureTimeouts,
+    /// Flag images less sharp than this fraction of the median of similar images
+    blur_threshold: f32,
+    recapture_blurred: bool,
+    motion_check: MotionCheck,
     camera_select_box_open: bool,
     images: Vec<ImagePreview>,
+    /// Plates of the empty table, kept apart from the object's images
+    backgrounds: Vec<ImagePreview>,
+    /// Pose and camera of the image picked in the gallery
+    selected: Option<(Option<u32>, u32, CameraId)>,
+    gallery_view: GalleryView,
     export_path: Arc<Mutex<Option<PathBuf>>>,
     file_picker_request: bool,
-    capture_delay_ms: u64,
+    settle: settle::SettleControls,
+    frame_set: frame_set::FrameSetControls,
+    masking: masking::MaskControls,
+    inspector: inspector::ImageInspector,
     table_cmd_tx: UnboundedSender<TurntableWorkerCommand>,
+    /// Emergency stop, signalled directly rather than queued behind other commands
+    table_estop: Arc<Notify>,
     table_state_rx: UnboundedReceiver<TurntableWorkerState>,
+    table_pose_rx: watch::Receiver<Option<Pose>>,
+    table_traffic_rx: UnboundedReceiver<Traffic>,
     camera_cmd_tx: UnboundedSender<CameraWorkerCommand>,
     camera_state_rx: broadcast::Receiver<CameraWorkerState>,
+    cameras_rx: watch::Receiver<Vec<ConnectedCamera>>,
     image_rx: UnboundedReceiver<ImagePreview>,
     export_job_tx: UnboundedSender<ExportJob>,
-    _marker: std::marker::PhantomData<T>,
+    export_failure_rx: UnboundedReceiver<String>,
+    /// Why poses of the last export failed, until dismissed
+    export_failures: Vec<String>,
 }
 
-impl<T: Turntable> TurntableApp<T> {
+impl TurntableApp {
     pub(crate) fn new(_cc: &CreationContext<'_>) -> Self {
         let (camera_cmd_tx, camera_cmd_rx) = mpsc::unbounded_channel();
-        let (camera_state_tx, camera_state_rx_1) = broadcast::channel(100);
-        let camera_state_rx_2 = camera_state_tx.subscribe();
+        let (camera_state_tx, camera_state_rx) = broadcast::channel(100);
+        let (cameras_tx, cameras_rx) = watch::channel(Vec::new());
         let (camera_imagepath_tx, camera_imagepath_rx) = mpsc::unbounded_channel();
 
         let (image_tx, image_rx) = mpsc::unbounded_channel();
 
         let (export_job_tx, export_job_rx) = mpsc::unbounded_channel();
+        let (export_failure_tx, export_failure_rx) = mpsc::unbounded_channel();
 
         let (table_cmd_tx, table_cmd_rx) = mpsc::unbounded_channel();
         let (table_state_tx, table_state_rx) = mpsc::unbounded_channel();
+        let (table_pose_tx, table_pose_rx) = watch::channel(None);
+        let (table_traffic_tx, table_traffic_rx) = mpsc::unbounded_channel();
+        let table_estop = Arc::new(Notify::new());
+        let table_estop_for_tt = table_estop.clone();
 
         // Spawn Tokio runtime for camera worker
         std::thread::spawn(move || {
             let rt = Runtime::new().unwrap();
-            let worker = CameraWorker::new(camera_cmd_rx, camera_state_tx, camera_imagepath_tx)
-                .expect("Could not create camera worker!");
+            let worker = CameraWorker::new(
+                camera_cmd_rx,
+                camera_state_tx,
+                camera_imagepath_tx,
+                cameras_tx,
+            )
+            .expect("Could not create camera worker!");
             rt.block_on(worker.run());
         });
 
         // Spawn Tokio runtime for turntable worker
         let camera_cmd_tx_for_tt = camera_cmd_tx.clone();
+        let cameras_rx_for_tt = cameras_rx.clone();
         std::thread::spawn(move || {
             let rt = Runtime::new().unwrap();
-            let worker = TurntableWorker::<T>::new(
+            let worker = TurntableWorker::new(
                 table_cmd_rx,
                 table_state_tx,
                 camera_cmd_tx_for_tt,
-                camera_state_rx_1,
+                cameras_rx_for_tt,
+                table_pose_tx,
+                TrafficLog::new(table_traffic_tx),
+                table_estop_for_tt,
             );
             rt.block_on(worker.run());
         });
@@ -152,48 +269,160 @@ impl<T: Turntable> TurntableApp<T> {
         // Spawn image exporter
         std::thread::spawn(move || {
             let rt = Runtime::new().unwrap();
-            rt.block_on(worker::image_exporter(export_job_rx));
+            rt.block_on(worker::image_exporter(export_job_rx, export_failure_tx));
         });
 
         Self {
             worker_state: TurntableWorkerState::Uninitialised,
+            driver_settings: DriverSettings::default(),
+            calibration_window_open: false,
+            calibration_detection: CompletionDetection::AngleQuery,
+            calibration: None,
+            last_job: None,
+            job_timings: BTreeMap::new(),
+            jog: jog::JogControls::default(),
+            console: console::ProtocolConsole::default(),
             camera_state: CameraWorkerState::Disconnected,
-            slider_steps: 24,
+            rotation_range: rotation_range::RotationRangeControls::default(),
             tilt_slider_low_deg: 0,
             tilt_slider_high_deg: 10,
             tilt_steps: 1,
-            selected_camera_spec: None,
+            job_speeds: DriverSettings::default().speed_limits().fastest,
+            pose_ordering: PoseOrdering::default(),
+            camera_trigger: CameraTrigger::Primary,
+            camera_timeouts: CaptureTimeouts::default(),
+            blur_threshold: 0.5,
+            recapture_blurred: false,
+            motion_check: MotionCheck::default(),
             camera_select_box_open: false,
             images: Vec::new(),
+            backgrounds: Vec::new(),
+            selected: None,
+            gallery_view: GalleryView::Grid,
             export_path: Arc::new(Mutex::new(None)),
             file_picker_request: false,
-            capture_delay_ms: 500,
+            settle: settle::SettleControls::default(),
+            frame_set: frame_set::FrameSetControls::default(),
+            masking: masking::MaskControls::default(),
+            inspector: inspector::ImageInspector::default(),
             table_cmd_tx,
+            table_estop,
             table_state_rx,
+            table_pose_rx,
+            table_traffic_rx,
             camera_cmd_tx,
-            camera_state_rx: camera_state_rx_2,
+            camera_state_rx,
+            cameras_rx,
             image_rx,
             export_job_tx,
-            _marker: std::marker::PhantomData,
+            export_failure_rx,
+            export_failures: Vec::new(),
         }
     }
 
     fn export_jobs(&self) -> Vec<ExportJob> {
         let output_directory = self.export_path.lock().unwrap();
+        // Each camera gets its own subdirectory once there's more than one
+        let several_cameras = self
+            .images
+            .iter()
+            .any(|img| img.camera != self.images[0].camera);
+        // Likewise each job, as they all number their poses from zero
+        let several_jobs = self.images.iter().any(|img| img.job != self.images[0].job);
         match output_directory.deref() {
             Some(output_directory) => self
-                .images
-                .iter()
-                .map(|img| ExportJob {
-                    image_path: img.path.clone(),
-                    seq: img.seq,
+                .image_groups()
+                .map(|group| ExportJob {
+                    capture_job: group[0].job.filter(|_| several_jobs),
+                    seq: group[0].seq,
+                    camera: several_cameras.then_some(group[0].camera),
+                    frames: group
+                        .iter()
+                        .map(|img| ExportFrame {
+                            image_path: img.path.clone(),
+                            shot: img.shot,
+                            setting: img.setting,
+                        })
+                        .collect(),
                     output_directory: output_directory.clone(),
+                    merge: self.frame_set.merge,
+                    masks: self.masking.mask_job(
+                        self.matching_plate(&group[0])
+                            .map(|plate| plate.iter().map(|img| img.path.clone()).collect()),
+                    ),
                 })
                 .collect(),
             None => Vec::new(),
         }
     }
 
+    /// Images grouped by pose and camera, e.g. the frames of a bracket or focus stack.
+    fn image_groups(&self) -> impl Iterator<Item = &[ImagePreview]> {
+        self.images.chunk_by(|a, b| a.pose_key() == b.pose_key())
+    }
+
+    /// Background plate frames from the same camera at the tilt nearest an image's.
+    /// Images not captured by a job only match when there's a single plate to choose from.
+    fn matching_plate(&self, image: &ImagePreview) -> Option<&[ImagePreview]> {
+        let mut plates = self
+            .backgrounds
+            .chunk_by(|a, b| a.pose_key() == b.pose_key())
+            .filter(|plate| plate[0].camera == image.camera);
+        match image.pose {
+            Some(pose) => plates.min_by(|a, b| {
+                let distance = |plate: &[ImagePreview]| {
+                    plate[0].pose.map_or(f32::INFINITY, |plate_pose| {
+                        (plate_pose.tilt_deg - pose.tilt_deg).abs()
+                    })
+                };
+                distance(a).total_cmp(&distance(b))
+            }),
+            None => plates.next().filter(|_| plates.next().is_none()),
+        }
+    }
+
+    /// The job described by the current capture settings.
+    fn stepping_job(&self, ordering: PoseOrdering) -> TurntableSteppingJob {
+        TurntableSteppingJob {
+            rotation: self.rotation_range.arc(),
+            tilt_lower: self.tilt_slider_low_deg as f32,
+            tilt_upper: self.tilt_slider_high_deg as f32,
+            tilt_steps: self.tilt_steps,
+            ordering,
+            settle: self.settle.settle_times(),
+            frames: self.frame_set.frame_set(),
+            cameras: self.camera_trigger,
+            recapture_below: self.recapture_blurred.then_some(self.blur_threshold),
+            motion_check: self.motion_check,
+            background: false,
+            speeds: self.job_speeds,
+            camera_timeouts: self.camera_timeouts,
+        }
+    }
+
+    /// Plates of the empty table at each of the current job's tilt rings.
+    fn background_job(&self) -> TurntableSteppingJob {
+        TurntableSteppingJob {
+            recapture_below: None,
+            motion_check: MotionCheck::Off,
+            background: true,
+            ..self.stepping_job(self.pose_ordering)
+        }
+    }
+
+    /// Flag images that are much less sharp than others from the same camera and point in a frame set.
+    fn update_blur_flags(&mut self) {
+        let scores: Vec<_> = self
+            .images
+            .iter()
+            .map(|img| ((img.camera, img.shot), img.sharpness))
+            .collect();
+        let flags = quality::below_median(&scores, self.blur_threshold);
+        for (img, blurred) in self.images.iter_mut().zip(flags) {
+            img.blurred = blurred;
+        }
+    }
+
     fn next_seq(&self) -> u32 {
         match self.images.iter().map(|img| img.seq).max() {
             Some(max) => max + 1,
@@ -202,38 +431,105 @@ impl<T: Turntable> TurntableApp<T> {
     }
 }
 
-impl<T: Turntable> App for TurntableApp<T> {
+impl App for TurntableApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
         // Drain any new worker states
         while let Ok(state) = self.table_state_rx.try_recv() {
+            match &state {
+                TurntableWorkerState::Calibrating(progress) => {
+                    self.calibration = Some(progress.clone());
+                }
+                TurntableWorkerState::Stepping(stepping_state)
+                | TurntableWorkerState::Paused(stepping_state) => {
+                    self.job_timings
+                        .insert(stepping_state.id(), stepping_state.timing().clone());
+                    self.last_job = Some(stepping_state.clone());
+                }
+                _ => {}
+            }
             self.worker_state = state;
         }
+        while let Ok(traffic) = self.table_traffic_rx.try_recv() {
+            self.console.record(traffic);
+        }
         while let Ok(state) = self.camera_state_rx.try_recv() {
             self.camera_state = state;
         }
+        while let Ok(failure) = self.export_failure_rx.try_recv() {
+            self.export_failures.push(failure);
+        }
         // Receive any new images from worker
         while let Ok(mut image) = self.image_rx.try_recv() {
-            match image.load_texture(ctx) {
-                Ok(_) => self.images.push(image),
-                Err(_) => eprintln!("Error loading decoded image {:?}", image.path),
+            if let Err(e) = image.load_texture(ctx) {
+                eprintln!("Error loading decoded image {:?}: {:?}", image.path, e);
+                continue;
             }
-            self.images.sort_by_key(|img| img.seq);
+            let images = match image.background {
+                true => &mut self.backgrounds,
+                false => &mut self.images,
+            };
+            // A recaptured pose replaces what was there
+            let key = inspector::key(&image);
+            images.retain(|img| inspector::key(img) != key);
+            images.push(image);
+            images.sort_by_key(inspector::key);
+            self.update_blur_flags();
+        }
+
+        // Emergency stop, always available. egui also uses Escape to close popups and leave text
+        // fields, so those presses are left to it
+        let escape_taken =
+            ctx.wants_keyboard_input() || ctx.memory(|memory| memory.any_popup_open());
+        if !escape_taken && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.table_estop.notify_one();
         }
 
         // Build UI
         egui::SidePanel::left("Turntable").show(ctx, |ui| {
             ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
+                ui.add_space(8.0);
+                let stop_btn = egui::Button::new(
+                    egui::RichText::new("STOP (Esc)")
+                        .strong()
+                        .color(Color32::WHITE),
+                )
+                .fill(Color32::DARK_RED)
+                .min_size(egui::vec2(220.0, 36.0));
+                if ui.add(stop_btn).clicked() {
+                    self.table_estop.notify_one();
+                }
+
+                // Driver selection, only changeable while disconnected
+                ui.add_space(8.0);
+                ui.add_enabled_ui(
+                    matches!(self.worker_state, TurntableWorkerState::Uninitialised),
+                    |ui| driver_settings::driver_settings_ui(ui, &mut self.driver_settings),
+                );
+                // Keep job speeds and tilts valid for the selected driver
+                let speed_limits = self.driver_settings.speed_limits();
+                self.job_speeds = speed_limits.clamp(self.job_speeds);
+                let max_tilt_deg = self.driver_settings.max_tilt_deg() as i16;
+                self.tilt_slider_low_deg =
+                    self.tilt_slider_low_deg.clamp(-max_tilt_deg, max_tilt_deg);
+                self.tilt_slider_high_deg =
+                    self.tilt_slider_high_deg.clamp(-max_tilt_deg, max_tilt_deg);
+
                 // Connect button
                 ui.add_space(8.0);
                 let (connect_btn, enabled, command) = match self.worker_state {
                     TurntableWorkerState::Uninitialised => (
                         egui::Button::new("Connect"),
                         true,
-                        Some(TurntableWorkerCommand::Connect),
+                        Some(TurntableWorkerCommand::Connect {
+                            settings: self.driver_settings.clone(),
+                        }),
                     ),
                     TurntableWorkerState::Connecting => {
                         (egui::Button::new("Connecting..."), false, None)
                     }
+                    TurntableWorkerState::Calibrating(_) => {
+                        (egui::Button::new("Disconnect"), false, None)
+                    }
                     _ => (
                         egui::Button::new("Disconnect"),
                         true,
@@ -244,6 +540,19 @@ impl<T: Turntable> App for TurntableApp<T> {
                 if ui.add_enabled(enabled, connect_btn).clicked() && command.is_some() {
                     let _ = self.table_cmd_tx.send(command.unwrap());
                 }
+                if ui
+                    .add_enabled(
+                        matches!(self.worker_state, TurntableWorkerState::Connected),
+                        egui::Button::new("Calibrate Timing..."),
+                    )
+                    .clicked()
+                {
+                    self.calibration = None;
+                    self.calibration_window_open = true;
+                }
+                if ui.button("Protocol Console...").clicked() {
+                    self.console.open = true;
+                }
 
                 // Progress indicator
                 ui.add_space(12.0);
@@ -254,6 +563,10 @@ impl<T: Turntable> App for TurntableApp<T> {
                     TurntableWorkerState::ReturningToResetPosition => 1.0,
                     TurntableWorkerState::Stepping(stepping_state) => stepping_state.progress(),
                     TurntableWorkerState::Paused(stepping_state) => stepping_state.progress(),
+                    TurntableWorkerState::Calibrating(progress) => {
+                        progress.move_index as f32 / progress.total_moves as f32
+                    }
+                    TurntableWorkerState::RotatingContinuously { .. } => 1.0,
                 };
 
                 let progress_bar = egui::ProgressBar::new(progress);
@@ -266,7 +579,21 @@ impl<T: Turntable> App for TurntableApp<T> {
                     TurntableWorkerState::Paused(_) => {
                         progress_bar.show_percentage().text("Paused")
                     }
+                    TurntableWorkerState::Calibrating(_) => progress_bar.text("Calibrating"),
+                    TurntableWorkerState::RotatingContinuously { .. } => progress_bar.animate(true),
                 });
+                if let Some(job) = &self.last_job {
+                    let running = matches!(
+                        self.worker_state,
+                        TurntableWorkerState::Stepping(_) | TurntableWorkerState::Paused(_)
+                    );
+                    job_stats::job_stats_ui(ui, job, running);
+                }
+                if let TurntableWorkerState::Paused(stepping_state) = &self.worker_state {
+                    if let Some(reason) = stepping_state.pause_reason() {
+                        ui.colored_label(ui.visuals().error_fg_color, reason);
+                    }
+                }
 
                 // Reset/step controls
                 ui.add_space(12.0);
@@ -329,13 +656,7 @@ impl<T: Turntable> App for TurntableApp<T> {
                                     .clicked()
                                 {
                                     let _ = self.table_cmd_tx.send(TurntableWorkerCommand::Step {
-                                        job: TurntableSteppingJob {
-                                            rotation_steps: self.slider_steps,
-                                            tilt_lower: self.tilt_slider_low_deg as f32,
-                                            tilt_upper: self.tilt_slider_high_deg as f32,
-                                            tilt_steps: self.tilt_steps,
-                                            capture_delay_ms: self.capture_delay_ms,
-                                        },
+                                        job: self.stepping_job(self.pose_ordering),
                                     });
                                 }
                             }
@@ -346,11 +667,7 @@ impl<T: Turntable> App for TurntableApp<T> {
                 // Step sliders
                 ui.add_space(12.0);
                 ui.vertical(|ui| {
-                    ui.add(egui::Label::new("Rotation steps:"));
-                    ui.horizontal(|ui| {
-                        ui.style_mut().spacing.slider_width = ui.available_width() - 60.0;
-                        ui.add(egui::Slider::new(&mut self.slider_steps, 1..=200).show_value(true));
-                    });
+                    self.rotation_range.ui(ui);
 
                     ui.add_space(8.0);
                     ui.add(egui::Label::new(format!(
@@ -372,7 +689,7 @@ impl<T: Turntable> App for TurntableApp<T> {
                             egui_double_slider::DoubleSlider::new(
                                 &mut self.tilt_slider_low_deg,
                                 &mut self.tilt_slider_high_deg,
-                                -30..=30,
+                                -max_tilt_deg..=max_tilt_deg,
                             )
                             .stroke(Stroke::new(7.0, ctx.style().visuals.selection.bg_fill))
                             .push_by_dragging(false)
@@ -380,6 +697,69 @@ impl<T: Turntable> App for TurntableApp<T> {
                             .width(ui.available_width()),
                         );
                     });
+
+                    ui.add_space(8.0);
+                    ui.add(egui::Label::new("Rotation speed:"));
+                    ui.horizontal(|ui| {
+                        ui.style_mut().spacing.slider_width = ui.available_width() - 90.0;
+                        ui.add(
+                            egui::Slider::new(
+                                &mut self.job_speeds.rotation_deg_per_sec,
+                                speed_limits.slowest.rotation_deg_per_sec
+                                    ..=speed_limits.fastest.rotation_deg_per_sec,
+                            )
+                            .max_decimals(2)
+                            .suffix(" deg/s"),
+                        );
+                    });
+                    ui.add(egui::Label::new("Tilt speed:"));
+                    ui.horizontal(|ui| {
+                        ui.style_mut().spacing.slider_width = ui.available_width() - 90.0;
+                        ui.add(
+                            egui::Slider::new(
+                                &mut self.job_speeds.tilt_deg_per_sec,
+                                speed_limits.slowest.tilt_deg_per_sec
+                                    ..=speed_limits.fastest.tilt_deg_per_sec,
+                            )
+                            .max_decimals(2)
+                            .suffix(" deg/s"),
+                        );
+                    });
+
+                    ui.add_space(8.0);
+                    ui.add(egui::Label::new("Pose order:"));
+                    let move_time_text = |ordering: PoseOrdering| {
+                        let move_time = self.stepping_job(ordering).estimated_move_time();
+                        format!(
+                            "{} (~{} moving)",
+                            ordering.name(),
+                            job_stats::format_duration(move_time)
+                        )
+                    };
+                    let mut ordering = self.pose_ordering;
+                    egui::ComboBox::from_id_salt("pose_ordering")
+                        .width(ui.available_width())
+                        .selected_text(move_time_text(ordering))
+                        .show_ui(ui, |ui| {
+                            for option in PoseOrdering::ALL {
+                                ui.selectable_value(&mut ordering, option, move_time_text(option));
+                            }
+                        });
+                    self.pose_ordering = ordering;
+                });
+
+                // Manual jog controls
+                ui.add_space(12.0);
+                egui::CollapsingHeader::new("Jog").show(ui, |ui| {
+                    let pose = *self.table_pose_rx.borrow();
+                    if let Some(command) = self.jog.ui(
+                        ui,
+                        &self.worker_state,
+                        pose,
+                        self.driver_settings.max_tilt_deg(),
+                    ) {
+                        let _ = self.table_cmd_tx.send(command);
+                    }
                 });
 
                 // Debug status
@@ -388,17 +768,18 @@ impl<T: Turntable> App for TurntableApp<T> {
             });
         });
         egui::CentralPanel::default().show(ctx, |ui| {
-            let selected_camera_name = match &self.selected_camera_spec {
-                Some(camera_spec) => camera_spec.name(),
-                _ => "Select a camera...".to_string(),
+            let connected_cameras = self.cameras_rx.borrow().clone();
+            let selected_camera_name = match connected_cameras.len() {
+                0 => "Select a camera...",
+                _ => "Add a camera...",
             };
 
             // Unique ID to track ComboBox open/close state
             let combo_id = "camera_combo";
-            let previous_selected_camera_spec = self.selected_camera_spec.clone();
+            let mut selected_camera_spec: Option<CameraSpec> = None;
             let mut camera_select_box_open = false;
             egui::ComboBox::new(combo_id, "Camera")
-                .selected_text(format!("{}", selected_camera_name))
+                .selected_text(selected_camera_name)
                 .show_ui(ui, |ui| {
                     // This closure is only run when the combo box is open.
                     camera_select_box_open = true;
@@ -417,17 +798,21 @@ impl<T: Turntable> App for TurntableApp<T> {
                         | CameraWorkerState::Failed => {}
                         CameraWorkerState::GettingCameraList
                         | CameraWorkerState::CameraConnecting
-                        | CameraWorkerState::Capturing { seq: _ } => {
+                        | CameraWorkerState::Capturing { seq: _ }
+                        | CameraWorkerState::Downloading { seq: _ } => {
                             ui.spinner();
                         }
                         CameraWorkerState::CamerasListed { cameras } => {
                             for camera in cameras {
-                                ui.selectable_value(
-                                    &mut self.selected_camera_spec,
-                                    Some(camera.clone()),
-                                    camera.name(),
-                                )
-                                .clicked();
+                                let connected =
+                                    connected_cameras.iter().any(|info| &info.spec == camera);
+                                ui.add_enabled_ui(!connected, |ui| {
+                                    ui.selectable_value(
+                                        &mut selected_camera_spec,
+                                        Some(camera.clone()),
+                                        camera.name(),
+                                    );
+                                });
                             }
                             if cameras.is_empty() {
                                 ui.label("No cameras found");
@@ -438,8 +823,7 @@ impl<T: Turntable> App for TurntableApp<T> {
                         CameraWorkerState::Ready
                         | CameraWorkerState::Failed
                         | CameraWorkerState::CamerasListed { .. } => {
-                            if ui.small_button("Disconnect").clicked() {
-                                self.selected_camera_spec = None;
+                            if ui.small_button("Disconnect all").clicked() {
                                 let _ = self.camera_cmd_tx.send(CameraWorkerCommand::Disconnect);
                             }
                         }
@@ -448,20 +832,111 @@ impl<T: Turntable> App for TurntableApp<T> {
                 });
             self.camera_select_box_open = camera_select_box_open;
 
-            if self.selected_camera_spec != previous_selected_camera_spec
-                && self.selected_camera_spec.is_some()
-            {
+            if let Some(camera_spec) = selected_camera_spec {
                 let _ = self
                     .camera_cmd_tx
-                    .send(CameraWorkerCommand::ConnectToCamera {
-                        camera_spec: self.selected_camera_spec.clone().unwrap(),
-                    });
+                    .send(CameraWorkerCommand::ConnectToCamera { camera_spec });
+            }
+            for camera in &connected_cameras {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}: {}", camera.id, camera.spec.name()));
+                    if !camera.responding {
+                        ui.colored_label(ui.visuals().error_fg_color, "not responding");
+                    }
+                    if (!camera.responding || self.camera_state == CameraWorkerState::Failed)
+                        && ui.small_button("Reconnect").clicked()
+                    {
+                        let _ = self
+                            .camera_cmd_tx
+                            .send(CameraWorkerCommand::ReconnectCamera { id: camera.id });
+                    }
+                    if ui.small_button("Disconnect").clicked() {
+                        let _ = self
+                            .camera_cmd_tx
+                            .send(CameraWorkerCommand::DisconnectCamera { id: camera.id });
+                    }
+                });
+            }
+            if connected_cameras.len() > 1 {
+                ui.horizontal(|ui| {
+                    for trigger in CameraTrigger::ALL {
+                        ui.radio_value(&mut self.camera_trigger, trigger, trigger.name());
+                    }
+                });
+                ui.label("Cameras are triggered one after another; gphoto2 can't fire them at the same instant.");
             }
 
-            ui.horizontal(|ui| {
-                ui.add(egui::Label::new("Delay between captures (ms):"));
-                ui.style_mut().spacing.slider_width = ui.available_width() - 50.0;
-                ui.add(egui::Slider::new(&mut self.capture_delay_ms, 0..=2000).show_value(true));
+            egui::CollapsingHeader::new("Settle times").show(ui, |ui| {
+                self.settle.ui(ui);
+            });
+            egui::CollapsingHeader::new("Frames per pose").show(ui, |ui| {
+                self.frame_set.ui(ui);
+            });
+            egui::CollapsingHeader::new("Sharpness check").show(ui, |ui| {
+                let threshold = ui.add(
+                    egui::Slider::new(&mut self.blur_threshold, 0.1..=0.9)
+                        .text("of median sharpness"),
+                );
+                if threshold.changed() {
+                    self.update_blur_flags();
+                }
+                ui.checkbox(
+                    &mut self.recapture_blurred,
+                    "Recapture flagged poses at the end of a job",
+                );
+            });
+            egui::CollapsingHeader::new("Motion check").show(ui, |ui| {
+                ui.label("When a capture looks the same as the previous pose's:");
+                ui.horizontal(|ui| {
+                    for option in MotionCheck::ALL {
+                        ui.radio_value(&mut self.motion_check, option, option.name());
+                    }
+                });
+                ui.label(
+                    "Captures are compared with how much the view changes between other poses. \
+                     A job's first moves are judged by the previous job's, and before any job has \
+                     run, only a table that didn't move at all is caught.",
+                );
+            });
+            egui::CollapsingHeader::new("Background & masks").show(ui, |ui| {
+                let plates = self
+                    .backgrounds
+                    .chunk_by(|a, b| a.pose_key() == b.pose_key())
+                    .count();
+                let can_capture = matches!(self.worker_state, TurntableWorkerState::Connected)
+                    && !connected_cameras.is_empty();
+                let selected = self
+                    .image_groups()
+                    .find(|group| Some(group[0].pose_key()) == self.selected)
+                    .map(|group| representative(group).path.clone());
+                if self
+                    .masking
+                    .ui(ui, plates, can_capture, selected.as_deref())
+                {
+                    self.backgrounds.clear();
+                    let _ = self.table_cmd_tx.send(TurntableWorkerCommand::Step {
+                        job: self.background_job(),
+                    });
+                }
+            });
+            egui::CollapsingHeader::new("Camera timeouts").show(ui, |ui| {
+                let previous_timeouts = self.camera_timeouts;
+                egui::Grid::new("camera_timeouts")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Capture:");
+                        ui.add(seconds(&mut self.camera_timeouts.capture, 120));
+                        ui.end_row();
+                        ui.label("Download:");
+                        ui.add(seconds(&mut self.camera_timeouts.download, 300));
+                        ui.end_row();
+                    });
+                ui.label("Cameras that take longer are cancelled and the job pauses.");
+                if self.camera_timeouts != previous_timeouts {
+                    let _ = self.camera_cmd_tx.send(CameraWorkerCommand::SetTimeouts {
+                        timeouts: self.camera_timeouts,
+                    });
+                }
             });
             let ui_width = ui.available_width() - 18.0;
             ui.allocate_ui_with_layout(
@@ -471,14 +946,23 @@ impl<T: Turntable> App for TurntableApp<T> {
                     let item_width = ui_width / 3.0;
                     let (capture_button, capture_button_enabled, capture_command) =
                         match &self.camera_state {
-                            CameraWorkerState::Ready => (
-                                egui::Button::new("Capture"),
-                                true,
-                                Some(CameraWorkerCommand::CaptureImage {
-                                    seq: self.next_seq(),
-                                    extra_delay_ms: 0,
-                                }),
-                            ),
+                            CameraWorkerState::Ready | CameraWorkerState::CamerasListed { .. }
+                                if !connected_cameras.is_empty() =>
+                            {
+                                (
+                                    egui::Button::new("Capture"),
+                                    true,
+                                    Some(CameraWorkerCommand::CaptureImage {
+                                        job: None,
+                                        seq: self.next_seq(),
+                                        pose: None,
+                                        background: false,
+                                        frames: None,
+                                        trigger: self.camera_trigger,
+                                        reply: None,
+                                    }),
+                                )
+                            }
                             _ => (egui::Button::new("Capture"), false, None),
                         };
                     ui.add_enabled_ui(capture_button_enabled, |ui| {
@@ -496,6 +980,7 @@ impl<T: Turntable> App for TurntableApp<T> {
                             .clicked()
                         {
                             self.images.clear();
+                            self.job_timings.clear();
                         }
                     });
                     ui.add_enabled_ui(export_clear_button_enable, |ui| {
@@ -508,6 +993,18 @@ impl<T: Turntable> App for TurntableApp<T> {
                     });
                 },
             );
+            if !self.export_failures.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("{} poses failed to export", self.export_failures.len()),
+                    )
+                    .on_hover_text(self.export_failures.join("\n"));
+                    if ui.button("Dismiss").clicked() {
+                        self.export_failures.clear();
+                    }
+                });
+            }
 
             // Launch the file picker outside the UI context
             if self.file_picker_request {
@@ -539,45 +1036,101 @@ impl<T: Turntable> App for TurntableApp<T> {
             if !export_jobs.is_empty() {
                 for job in &export_jobs {
                     eprintln!(
-                        "Running export job for image {:?} -> {:?}",
-                        job.image_path, job.seq
+                        "Running export job for {} image(s) -> {:?}",
+                        job.frames.len(),
+                        job.seq
                     );
                     let _ = self.export_job_tx.send(job.clone());
                 }
+                self.export_failures.clear();
+                let manifest_path = export_jobs[0].output_directory.join("manifest.json");
+                let manifest = worker::export_manifest(&export_jobs);
+                std::thread::spawn(move || {
+                    if let Err(e) = std::fs::write(&manifest_path, manifest) {
+                        eprintln!("Couldn't write manifest to {:?}: {:?}", manifest_path, e);
+                    }
+                });
+                // Keep each job's timings with its images, to compare cameras and setups
+                let several_jobs = export_jobs.iter().any(|job| job.capture_job.is_some());
+                for (&job, timing) in &self.job_timings {
+                    if !self.images.iter().any(|img| img.job == Some(job)) {
+                        continue;
+                    }
+                    let mut path = export_jobs[0].output_directory.clone();
+                    if several_jobs {
+                        path.push(worker::job_directory(job));
+                    }
+                    let csv = timing.to_csv();
+                    std::thread::spawn(move || {
+                        let written = std::fs::create_dir_all(&path)
+                            .and_then(|()| std::fs::write(path.join("timings.csv"), csv));
+                        if let Err(e) = written {
+                            eprintln!("Couldn't write timings to {:?}: {:?}", path, e);
+                        }
+                    });
+                }
                 let mut export_path = self.export_path.lock().unwrap();
                 *export_path = None;
                 eprintln!("Exported {} images", export_jobs.len());
             }
 
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                const COLUMNS: usize = 4;
-                const SPACING: f32 = 10.0;
-                let img_width = ui.available_width() / (COLUMNS as f32) - SPACING;
-
-                egui::Grid::new("image_grid")
-                    .num_columns(COLUMNS)
-                    .spacing([SPACING, SPACING])
-                    .show(ui, |ui| {
-                        for (i, image) in self.images.iter().enumerate() {
-                            match &image.texture {
-                                Some(texture) => {
-                                    ui.add(
-                                        egui::Image::new(ImageSource::Texture(
-                                            SizedTexture::from_handle(&texture),
-                                        ))
-                                        .max_width(img_width),
-                                    );
-                                    if i > 0 && (i + 1) % 4 == 0 {
-                                        ui.end_row();
-                                    }
-                                }
-                                None => {}
-                            };
-                        }
-                    })
-            })
+            ui.horizontal(|ui| {
+                for view in GalleryView::ALL {
+                    ui.radio_value(&mut self.gallery_view, view, view.name());
+                }
+            });
+            let mut clicked = None;
+            match (self.gallery_view, &self.last_job) {
+                (GalleryView::PoseMatrix, Some(job)) => {
+                    let running = matches!(
+                        self.worker_state,
+                        TurntableWorkerState::Stepping(_) | TurntableWorkerState::Paused(_)
+                    );
+                    // Plates aren't among the images the inspector steps through
+                    let images = match job.is_background() {
+                        true => &self.backgrounds,
+                        false => &self.images,
+                    };
+                    clicked = pose_matrix::pose_matrix_ui(ui, job, images, running, self.selected)
+                        .filter(|_| !job.is_background());
+                }
+                (GalleryView::PoseMatrix, None) => {
+                    ui.label("Run a job to see its poses by tilt ring and rotation.");
+                }
+                (GalleryView::Grid, _) => {
+                    clicked = gallery_grid::gallery_grid_ui(
+                        ui,
+                        &self.images,
+                        self.last_job.as_ref(),
+                        self.selected,
+                    );
+                }
+            }
+            if let Some((job, seq, camera, shot)) = clicked {
+                self.selected = Some((job, seq, camera));
+                self.inspector.open((job, seq, camera, shot));
+            }
         });
 
+        // The window can't be closed mid-calibration, as moves may need confirming
+        if matches!(self.worker_state, TurntableWorkerState::Calibrating(_)) {
+            self.calibration_window_open = true;
+        }
+        if let Some(command) = calibration::calibration_window(
+            ctx,
+            &mut self.calibration_window_open,
+            &mut self.calibration_detection,
+            &self.worker_state,
+            self.calibration.as_ref(),
+        ) {
+            let _ = self.table_cmd_tx.send(command);
+        }
+
+        self.inspector.window(ctx, &self.images);
+        if let Some(command) = self.console.window(ctx, &self.worker_state) {
+            let _ = self.table_cmd_tx.send(command);
+        }
+
         // keep repainting so progress animates
         ctx.request_repaint();
     }
@@ -0,0 +1,185 @@
+//! Gallery view laying out a job's images by tilt ring and rotation position, to spot missing poses.
+
+use std::collections::HashMap;
+
+use eframe::egui::{self, Color32, Sense, Stroke, StrokeKind, Vec2};
+
+use super::inspector::{self, ImageKey};
+use super::{representative, ImagePreview};
+use crate::app::worker::{CameraId, TurntableSteppingState};
+
+const MIN_CELL_WIDTH: f32 = 48.0;
+const MAX_CELL_WIDTH: f32 = 160.0;
+/// Room for the tilt label at the start of each row
+const LABEL_WIDTH: f32 = 48.0;
+const SPACING: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CellState {
+    Captured,
+    /// Not reached yet
+    Pending,
+    /// Passed without images, or paused here by an error
+    Failed,
+    /// Possibly blurred, or the table didn't move
+    Flagged,
+}
+
+impl CellState {
+    const ALL: [Self; 4] = [Self::Captured, Self::Pending, Self::Failed, Self::Flagged];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Captured => "Captured",
+            Self::Pending => "Pending",
+            Self::Failed => "Failed",
+            Self::Flagged => "Flagged",
+        }
+    }
+
+    fn color(self, visuals: &egui::Visuals) -> Color32 {
+        match self {
+            Self::Captured => Color32::from_rgb(60, 160, 80),
+            Self::Pending => visuals.widgets.inactive.bg_fill,
+            Self::Failed => visuals.error_fg_color,
+            Self::Flagged => visuals.warn_fg_color,
+        }
+    }
+}
+
+/// Show the job's poses as a row per tilt ring and a column per rotation position, with the pose the
+/// table is at outlined while `running`. Returns the image clicked, if any.
+pub(super) fn pose_matrix_ui(
+    ui: &mut egui::Ui,
+    job: &TurntableSteppingState,
+    images: &[ImagePreview],
+    running: bool,
+    selected: Option<(Option<u32>, u32, CameraId)>,
+) -> Option<ImageKey> {
+    let grid = job.grid();
+    let steps: HashMap<(usize, usize), usize> = grid
+        .cells
+        .iter()
+        .enumerate()
+        .map(|(step, &cell)| (cell, step))
+        .collect();
+    // The first camera's images stand for each pose, as each camera would need a matrix of its own.
+    // Earlier jobs number their poses the same way, so only this job's images are shown
+    let mut poses: Vec<Option<&[ImagePreview]>> = vec![None; grid.cells.len()];
+    let mut blurred = vec![false; grid.cells.len()];
+    for group in images
+        .chunk_by(|a, b| a.pose_key() == b.pose_key())
+        .filter(|group| group[0].job == Some(job.id()))
+    {
+        let Some(pose) = poses.get_mut(group[0].seq as usize) else {
+            continue;
+        };
+        pose.get_or_insert(group);
+        blurred[group[0].seq as usize] |= group.iter().any(|img| img.blurred);
+    }
+
+    ui.horizontal(|ui| {
+        for state in CellState::ALL {
+            let (rect, _) = ui.allocate_exact_size(Vec2::splat(12.0), Sense::hover());
+            ui.painter()
+                .rect_filled(rect, 2.0, state.color(ui.visuals()));
+            ui.label(state.name());
+        }
+    });
+
+    let columns = grid.rotations_deg.len();
+    let cell_width = ((ui.available_width() - LABEL_WIDTH) / columns as f32 - SPACING)
+        .clamp(MIN_CELL_WIDTH, MAX_CELL_WIDTH);
+    let cell_size = Vec2::new(cell_width, cell_width * 2.0 / 3.0);
+    let mut clicked = None;
+    egui::ScrollArea::both().show(ui, |ui| {
+        egui::Grid::new("pose_matrix")
+            .num_columns(columns + 1)
+            .spacing([SPACING, SPACING])
+            .show(ui, |ui| {
+                ui.label("");
+                for rotation_deg in &grid.rotations_deg {
+                    ui.label(format!("{:.0} deg", rotation_deg));
+                }
+                ui.end_row();
+
+                for (ring, tilt_deg) in grid.tilts_deg.iter().enumerate() {
+                    ui.label(format!("{:+.0} deg", tilt_deg));
+                    for position in 0..columns {
+                        let Some(&step) = steps.get(&(ring, position)) else {
+                            // Rings don't all have every position, e.g. for background plates
+                            ui.label("");
+                            continue;
+                        };
+                        let group = poses[step];
+                        let step = step as u32;
+                        let pause_reason =
+                            job.pause_reason().filter(|_| job.overall_step() == step);
+                        let flagged = blurred[step as usize] || job.unmoved_steps().contains(&step);
+                        let state = cell_state(
+                            group.is_some(),
+                            flagged,
+                            job.passed(step),
+                            pause_reason.is_some(),
+                        );
+
+                        let (rect, response) = ui.allocate_exact_size(cell_size, Sense::click());
+                        let color = state.color(ui.visuals());
+                        match group
+                            .map(representative)
+                            .and_then(|img| img.texture.as_ref().map(|texture| (img, texture)))
+                        {
+                            Some((image, texture)) => {
+                                egui::Image::new(texture).paint_at(ui, rect);
+                                ui.painter().rect_stroke(
+                                    rect,
+                                    2.0,
+                                    Stroke::new(2.0, color),
+                                    StrokeKind::Inside,
+                                );
+                                if response.clicked() {
+                                    clicked = Some(inspector::key(image));
+                                }
+                                if selected == Some(image.pose_key()) {
+                                    ui.painter().rect_stroke(
+                                        rect,
+                                        2.0,
+                                        ui.visuals().selection.stroke,
+                                        StrokeKind::Outside,
+                                    );
+                                }
+                            }
+                            None => {
+                                ui.painter().rect_filled(rect, 2.0, color);
+                            }
+                        }
+                        if running && job.overall_step() == step {
+                            ui.painter().rect_stroke(
+                                rect.expand(2.0),
+                                2.0,
+                                Stroke::new(3.0, ui.visuals().strong_text_color()),
+                                StrokeKind::Outside,
+                            );
+                        }
+                        let mut hover = format!("Pose {}: {}", step, state.name());
+                        if let Some(reason) = pause_reason {
+                            hover += &format!("\n{}", reason);
+                        }
+                        response.on_hover_text(hover);
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+    clicked
+}
+
+/// How a pose is going: whether it has images, and whether they look right.
+fn cell_state(has_images: bool, flagged: bool, passed: bool, paused_here: bool) -> CellState {
+    match (has_images, flagged) {
+        (true, true) => CellState::Flagged,
+        (true, false) => CellState::Captured,
+        (false, _) if passed || paused_here => CellState::Failed,
+        (false, _) => CellState::Pending,
+    }
+}
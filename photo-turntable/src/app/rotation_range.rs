@@ -0,0 +1,97 @@
+//! Controls for the range of rotations captured on each tilt ring.
+
+use eframe::egui::{self, DragValue};
+
+use crate::app::worker::{DetailSector, RotationArc, RotationSpacing};
+
+/// Rotation range UI state. Both spacing settings are kept so switching between them doesn't lose either.
+pub(super) struct RotationRangeControls {
+    start_deg: f32,
+    end_deg: f32,
+    steps: u16,
+    spacing_deg: f32,
+    use_spacing: bool,
+    use_detail: bool,
+    detail: DetailSector,
+}
+
+impl Default for RotationRangeControls {
+    fn default() -> Self {
+        Self {
+            start_deg: 0.0,
+            end_deg: 360.0,
+            steps: 24,
+            spacing_deg: 15.0,
+            use_spacing: false,
+            use_detail: false,
+            detail: DetailSector {
+                start_deg: 60.0,
+                end_deg: 120.0,
+                spacing_deg: 5.0,
+            },
+        }
+    }
+}
+
+impl RotationRangeControls {
+    /// The rotation arc described by the current settings.
+    pub(super) fn arc(&self) -> RotationArc {
+        RotationArc {
+            start_deg: self.start_deg,
+            end_deg: self.end_deg,
+            spacing: match self.use_spacing {
+                true => RotationSpacing::Degrees(self.spacing_deg),
+                false => RotationSpacing::Steps(self.steps),
+            },
+            detail: self.use_detail.then_some(self.detail),
+        }
+    }
+
+    pub(super) fn ui(&mut self, ui: &mut egui::Ui) {
+        let positions = self.arc().angles().len();
+        ui.add(egui::Label::new(format!(
+            "Rotation: {} position{}",
+            positions,
+            if positions == 1 { "" } else { "s" }
+        )));
+        ui.horizontal(|ui| {
+            ui.label("From");
+            ui.add(degrees(&mut self.start_deg).range(0.0..=self.end_deg));
+            ui.label("to");
+            ui.add(degrees(&mut self.end_deg).range(self.start_deg..=360.0));
+        });
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.use_spacing, false, "Steps");
+            ui.radio_value(&mut self.use_spacing, true, "Every");
+            if self.use_spacing {
+                ui.add(degrees(&mut self.spacing_deg).range(0.1..=360.0));
+            }
+        });
+        if !self.use_spacing {
+            ui.horizontal(|ui| {
+                ui.style_mut().spacing.slider_width = ui.available_width() - 60.0;
+                ui.add(egui::Slider::new(&mut self.steps, 1..=200).show_value(true));
+            });
+        }
+
+        ui.checkbox(&mut self.use_detail, "Denser detail sector");
+        if self.use_detail {
+            let detail = &mut self.detail;
+            ui.horizontal(|ui| {
+                ui.add(degrees(&mut detail.start_deg).range(self.start_deg..=detail.end_deg));
+                ui.label("to");
+                ui.add(degrees(&mut detail.end_deg).range(detail.start_deg..=self.end_deg));
+                ui.label("every");
+                ui.add(degrees(&mut detail.spacing_deg).range(0.1..=360.0));
+            });
+        }
+    }
+}
+
+fn degrees(value: &mut f32) -> DragValue<'_> {
+    DragValue::new(value)
+        .speed(0.5)
+        .max_decimals(1)
+        .suffix(" deg")
+}
@@ -0,0 +1,95 @@
+//! Controls for how long to let the object settle before each capture.
+
+use eframe::egui::{self, DragValue};
+
+use crate::app::worker::SettleTimes;
+use crate::turntable::calibration::AxisTiming;
+
+/// Settle time UI state.
+pub(super) struct SettleControls {
+    first_pose_ms: u64,
+    rotation_ms: f32,
+    tilt_ms: f32,
+    /// Lengthen settle times for bigger moves
+    scale_by_move: bool,
+    rotation_ms_per_deg: f32,
+    tilt_ms_per_deg: f32,
+}
+
+impl Default for SettleControls {
+    fn default() -> Self {
+        Self {
+            first_pose_ms: 1000,
+            rotation_ms: 500.0,
+            tilt_ms: 1500.0,
+            scale_by_move: false,
+            rotation_ms_per_deg: 10.0,
+            tilt_ms_per_deg: 50.0,
+        }
+    }
+}
+
+impl SettleControls {
+    /// The settle times described by the current settings.
+    pub(super) fn settle_times(&self) -> SettleTimes {
+        let per_deg = |ms_per_deg| match self.scale_by_move {
+            true => ms_per_deg,
+            false => 0.0,
+        };
+        SettleTimes {
+            first_pose_ms: self.first_pose_ms,
+            rotation: AxisTiming {
+                constant_ms: self.rotation_ms,
+                ms_per_deg: per_deg(self.rotation_ms_per_deg),
+            },
+            tilt: AxisTiming {
+                constant_ms: self.tilt_ms,
+                ms_per_deg: per_deg(self.tilt_ms_per_deg),
+            },
+        }
+    }
+
+    pub(super) fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("settle_times")
+            .num_columns(3)
+            .show(ui, |ui| {
+                ui.label("First pose:");
+                ui.add(
+                    DragValue::new(&mut self.first_pose_ms)
+                        .range(0..=10_000)
+                        .suffix(" ms"),
+                );
+                ui.end_row();
+
+                ui.label("After rotating:");
+                ui.add(milliseconds(&mut self.rotation_ms));
+                if self.scale_by_move {
+                    ui.add(ms_per_deg(&mut self.rotation_ms_per_deg));
+                }
+                ui.end_row();
+
+                ui.label("After tilting:");
+                ui.add(milliseconds(&mut self.tilt_ms));
+                if self.scale_by_move {
+                    ui.add(ms_per_deg(&mut self.tilt_ms_per_deg));
+                }
+                ui.end_row();
+            });
+        ui.checkbox(&mut self.scale_by_move, "Add time per degree moved");
+    }
+}
+
+fn milliseconds(value: &mut f32) -> DragValue<'_> {
+    DragValue::new(value)
+        .range(0.0..=10_000.0)
+        .speed(10.0)
+        .max_decimals(0)
+        .suffix(" ms")
+}
+
+fn ms_per_deg(value: &mut f32) -> DragValue<'_> {
+    DragValue::new(value)
+        .range(0.0..=1000.0)
+        .prefix("+ ")
+        .suffix(" ms/deg")
+}
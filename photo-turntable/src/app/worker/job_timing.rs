@@ -0,0 +1,169 @@
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Phases of capturing a single pose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Phase {
+    /// Moving the table to the pose
+    Move,
+    /// Waiting for the object to stop wobbling
+    Settle,
+    /// Triggering the shutter, until the camera has an image
+    Capture,
+    /// Fetching the image from the camera
+    Download,
+}
+
+impl Phase {
+    pub(crate) const ALL: [Phase; 4] =
+        [Phase::Move, Phase::Settle, Phase::Capture, Phase::Download];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Phase::Move => "Move",
+            Phase::Settle => "Settle",
+            Phase::Capture => "Capture",
+            Phase::Download => "Download",
+        }
+    }
+}
+
+/// Time spent in each phase for one pose.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct PoseTiming {
+    pub(crate) move_time: Duration,
+    pub(crate) settle_time: Duration,
+    pub(crate) capture_time: Duration,
+    pub(crate) download_time: Duration,
+}
+
+impl PoseTiming {
+    pub(crate) fn phase(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Move => self.move_time,
+            Phase::Settle => self.settle_time,
+            Phase::Capture => self.capture_time,
+            Phase::Download => self.download_time,
+        }
+    }
+
+    fn phase_mut(&mut self, phase: Phase) -> &mut Duration {
+        match phase {
+            Phase::Move => &mut self.move_time,
+            Phase::Settle => &mut self.settle_time,
+            Phase::Capture => &mut self.capture_time,
+            Phase::Download => &mut self.download_time,
+        }
+    }
+
+    /// Add time spent on a phase.
+    pub(crate) fn add(&mut self, phase: Phase, time: Duration) {
+        *self.phase_mut(phase) += time;
+    }
+
+    pub(crate) fn total(&self) -> Duration {
+        Phase::ALL.iter().map(|&phase| self.phase(phase)).sum()
+    }
+}
+
+/// Phase timings for every pose of a job so far.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct JobTiming {
+    /// Wall clock time the job was started
+    pub(crate) started: SystemTime,
+    /// Indexed by step. The last pose may still be in progress
+    pub(crate) poses: Vec<PoseTiming>,
+    /// Number of poses whose image has been captured and downloaded
+    pub(crate) completed: usize,
+}
+
+impl JobTiming {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: SystemTime::now(),
+            poses: Vec::new(),
+            completed: 0,
+        }
+    }
+
+    /// Add time spent on a phase of the given step.
+    pub(crate) fn record(&mut self, step: u32, phase: Phase, time: Duration) {
+        let step = step as usize;
+        if self.poses.len() <= step {
+            self.poses.resize(step + 1, PoseTiming::default());
+        }
+        self.poses[step].add(phase, time);
+    }
+
+    /// Mark the given step's image as captured.
+    pub(crate) fn complete(&mut self, step: u32) {
+        self.completed = self.completed.max(step as usize + 1);
+    }
+
+    /// Average time per phase over completed poses.
+    pub(crate) fn mean(&self) -> Option<PoseTiming> {
+        let completed = &self.poses[..self.completed.min(self.poses.len())];
+        if completed.is_empty() {
+            return None;
+        }
+        let mut mean = PoseTiming::default();
+        for phase in Phase::ALL {
+            *mean.phase_mut(phase) = completed
+                .iter()
+                .map(|pose| pose.phase(phase))
+                .sum::<Duration>()
+                / completed.len() as u32;
+        }
+        Some(mean)
+    }
+
+    /// Time since the job was started, including any time spent paused.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.started.elapsed().unwrap_or_default()
+    }
+
+    /// Estimated time to capture the remaining poses, from the average so far.
+    pub(crate) fn remaining(&self, total_steps: u32) -> Option<Duration> {
+        let remaining = (total_steps as usize).saturating_sub(self.completed);
+        self.mean().map(|mean| mean.total() * remaining as u32)
+    }
+
+    /// Per-pose timings as CSV, in milliseconds.
+    pub(crate) fn to_csv(&self) -> String {
+        let mut csv = format!(
+            "# started {}\nstep,move_ms,settle_ms,capture_ms,download_ms\n",
+            self.started
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        );
+        for (step, pose) in self.poses.iter().enumerate() {
+            let _ = write!(csv, "{}", step);
+            for phase in Phase::ALL {
+                let _ = write!(csv, ",{}", pose.phase(phase).as_millis());
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remaining_estimate() {
+        let mut timing = JobTiming::new();
+        assert_eq!(timing.remaining(4), None);
+        for (step, secs) in [(0, 1), (1, 3)] {
+            timing.record(step, Phase::Move, Duration::from_secs(secs));
+            timing.record(step, Phase::Download, Duration::from_secs(1));
+            timing.complete(step);
+        }
+        // Move to the next pose has started, but isn't counted until it's captured
+        timing.record(2, Phase::Move, Duration::from_secs(60));
+        assert_eq!(timing.mean().unwrap().move_time, Duration::from_secs(2));
+        assert_eq!(timing.remaining(4), Some(Duration::from_secs(6)));
+    }
+}
@@ -1,10 +1,20 @@
+mod job_timing;
 mod worker_camera;
 mod worker_image_loader;
 mod worker_turntable;
 
-pub(crate) use worker_camera::{CameraWorker, CameraWorkerCommand, CameraWorkerState};
+pub(crate) use job_timing::{JobTiming, Phase};
+pub(crate) use worker_camera::{
+    CameraId, CameraTrigger, CameraWorker, CameraWorkerCommand, CameraWorkerState, CaptureTimeouts,
+    ConnectedCamera, ImageHandle,
+};
 pub(crate) use worker_turntable::{
-    TurntableSteppingJob, TurntableWorker, TurntableWorkerCommand, TurntableWorkerState,
+    CalibrationProgress, CompletionDetection, DetailSector, MotionCheck, PoseOrdering, RotationArc,
+    RotationSpacing, SettleTimes, TurntableSteppingJob, TurntableSteppingState, TurntableWorker,
+    TurntableWorkerCommand, TurntableWorkerState,
 };
 
-pub(crate) use worker_image_loader::{image_exporter, image_loader, ExportJob};
+pub(crate) use worker_image_loader::{
+    export_manifest, image_exporter, image_loader, job_directory, load_image, load_thumbnail,
+    ExportFrame, ExportJob, MaskJob, MaskMethod, MaskNaming,
+};
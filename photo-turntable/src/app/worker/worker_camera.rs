@@ -1,41 +1,224 @@
-use std::{env, path::PathBuf, time::Duration};
+use std::{env, fmt, path::PathBuf, time::Duration};
 
-use crate::camera::{Camera, CameraContext, CameraSpec};
+use crate::camera::{
+    BracketMethod, Camera, CameraContext, CameraSpec, CameraTimeout, FrameSet, FrameSetting,
+};
+use crate::turntable::Pose;
 use anyhow::Error;
+use gphoto2::file::CameraFilePath;
 use mime2ext::mime2ext;
+use serde::Serialize;
 use tokio::{
     fs::{self},
     sync::{
         broadcast,
         mpsc::{UnboundedReceiver, UnboundedSender},
+        oneshot, watch,
     },
-    time::sleep,
+    time::{sleep, Instant},
 };
 use uuid::Uuid;
 
+/// Time for the lens to finish moving after driving focus.
+const FOCUS_DRIVE_SETTLE: Duration = Duration::from_millis(300);
+
+/// Longest to wait for a camera to open before giving up on it.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Identifies a camera for as long as the worker runs, so images from each rig can be kept apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub(crate) struct CameraId(pub(crate) u16);
+
+impl fmt::Display for CameraId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cam{}", self.0)
+    }
+}
+
+/// A camera the worker is connected to.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ConnectedCamera {
+    pub(crate) id: CameraId,
+    pub(crate) spec: CameraSpec,
+    /// Cleared when the camera times out, until it's reconnected
+    pub(crate) responding: bool,
+}
+
+/// Longest to wait on a camera before cancelling the operation and treating the camera as hung.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CaptureTimeouts {
+    /// From triggering the shutter until the camera has an image, including mirror lockup
+    pub(crate) capture: Duration,
+    /// For fetching one image from the camera
+    pub(crate) download: Duration,
+}
+
+impl Default for CaptureTimeouts {
+    fn default() -> Self {
+        Self {
+            capture: Duration::from_secs(30),
+            download: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Which of the connected cameras to trigger for each capture.
+///
+/// gphoto2 runs every camera operation on a single thread, so cameras can only be triggered one after
+/// another, never at the same instant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CameraTrigger {
+    /// Only the first camera connected
+    Primary,
+    /// Every camera, one after another
+    Sequential,
+}
+
+impl CameraTrigger {
+    pub(crate) const ALL: [CameraTrigger; 2] = [CameraTrigger::Primary, CameraTrigger::Sequential];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            CameraTrigger::Primary => "First camera only",
+            CameraTrigger::Sequential => "All cameras in turn",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ImageHandle {
+    /// Job that captured the image, as each job numbers its `seq` from zero
+    pub(crate) job: Option<u32>,
     pub(crate) seq: u32,
+    pub(crate) camera: CameraId,
+    /// Index of the frame among those captured for the same `seq`
+    pub(crate) shot: u16,
+    /// How this frame differs from others at the same `seq`, if there are several
+    pub(crate) setting: Option<FrameSetting>,
+    /// Table pose relative to where the job started, if captured by a job
+    pub(crate) pose: Option<Pose>,
+    /// A plate of the empty table, for masking the object in images at the same tilt
+    pub(crate) background: bool,
     pub(crate) path: PathBuf,
 }
 
+/// Everything captured in reply to one `CaptureImage` command.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CaptureReport {
+    pub(crate) images: Vec<ImageHandle>,
+    /// Time spent triggering the shutter, over every camera
+    pub(crate) capture_time: Duration,
+    /// Time spent fetching images, over every camera
+    pub(crate) download_time: Duration,
+}
+
+impl CaptureReport {
+    /// Add the report of another camera, whose capture ran after this one.
+    fn combine(&mut self, other: CaptureReport) {
+        self.images.extend(other.images);
+        self.capture_time += other.capture_time;
+        self.download_time += other.download_time;
+    }
+}
+
+/// Why a `CaptureImage` command produced no images.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CaptureError {
+    NoCamera,
+    /// An earlier capture failed, and the camera hasn't been reconnected since
+    CameraFailed,
+    /// The worker was busy, e.g. connecting a camera
+    Busy,
+    /// The camera couldn't take a picture
+    Capture(String),
+    /// Pictures were taken, but not every one could be fetched from the camera
+    Download(String),
+    /// These cameras didn't respond in time, and need reconnecting
+    TimedOut {
+        cameras: Vec<CameraId>,
+        message: String,
+    },
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::NoCamera => write!(f, "No camera is connected"),
+            CaptureError::CameraFailed => {
+                write!(f, "Camera failed on an earlier capture, reconnect it")
+            }
+            CaptureError::Busy => write!(f, "Camera worker is busy"),
+            CaptureError::Capture(message) => write!(f, "Capture failed: {}", message),
+            CaptureError::Download(message) => write!(f, "Download failed: {}", message),
+            CaptureError::TimedOut { message, .. } => {
+                write!(f, "Camera stopped responding, reconnect it: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// A frame was captured, but couldn't be fetched from the camera.
+#[derive(Debug)]
+struct DownloadFailed;
+
+impl fmt::Display for DownloadFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Couldn't download the image")
+    }
+}
+
+pub(crate) type CaptureReply = oneshot::Sender<Result<CaptureReport, CaptureError>>;
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum CameraWorkerState {
     Disconnected,
     GettingCameraList,
-    CamerasListed { cameras: Vec<CameraSpec> },
+    CamerasListed {
+        cameras: Vec<CameraSpec>,
+    },
     CameraConnecting,
     Ready,
     Failed,
-    Capturing { seq: u32 },
+    Capturing {
+        seq: u32,
+    },
+    /// The picture has been taken, and is being fetched from the camera
+    Downloading {
+        seq: u32,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub(crate) enum CameraWorkerCommand {
     ListCameras,
-    ConnectToCamera { camera_spec: CameraSpec },
+    ConnectToCamera {
+        camera_spec: CameraSpec,
+    },
+    /// Disconnect every camera
     Disconnect,
-    CaptureImage { seq: u32, extra_delay_ms: u64 },
+    DisconnectCamera {
+        id: CameraId,
+    },
+    /// Reopen a camera that failed or stopped responding, keeping its ID
+    ReconnectCamera {
+        id: CameraId,
+    },
+    SetTimeouts {
+        timeouts: CaptureTimeouts,
+    },
+    /// Capture a single image, or every frame of a set, on each triggered camera.
+    /// The reply is sent once all of them have finished downloading.
+    CaptureImage {
+        job: Option<u32>,
+        seq: u32,
+        pose: Option<Pose>,
+        background: bool,
+        frames: Option<FrameSet>,
+        trigger: CameraTrigger,
+        reply: Option<CaptureReply>,
+    },
 }
 
 struct CameraWorkerStateData {
@@ -48,9 +231,14 @@ struct CameraWorkerStateData {
 
 impl CameraWorkerStateData {
     fn update(&mut self, new_state: CameraWorkerState) {
-        eprintln!("Publishing state {:?}", new_state);
+        self.publish(new_state.clone());
         self.state = new_state;
-        let _ = self.state_tx.send(self.state.clone());
+    }
+
+    /// Publish a transient state without recording it, e.g. while each of several cameras captures.
+    fn publish(&self, state: CameraWorkerState) {
+        eprintln!("Publishing state {:?}", state);
+        let _ = self.state_tx.send(state);
     }
 }
 
@@ -60,8 +248,13 @@ pub(crate) struct CameraWorker {
     state: CameraWorkerStateData,
     /// Sender for pushing paths of saved images
     imagepath_tx: UnboundedSender<ImageHandle>,
+    /// Sender for pushing the list of connected cameras
+    cameras_tx: watch::Sender<Vec<ConnectedCamera>>,
     camera_context: CameraContext,
-    camera: Option<Camera>,
+    /// In the order they were connected
+    cameras: Vec<(ConnectedCamera, Camera)>,
+    next_camera_id: u16,
+    timeouts: CaptureTimeouts,
 }
 
 impl CameraWorker {
@@ -69,6 +262,7 @@ impl CameraWorker {
         cmd_rx: UnboundedReceiver<CameraWorkerCommand>,
         state_tx: broadcast::Sender<CameraWorkerState>,
         imagepath_tx: UnboundedSender<ImageHandle>,
+        cameras_tx: watch::Sender<Vec<ConnectedCamera>>,
     ) -> Result<Self, Error> {
         Ok(Self {
             state: CameraWorkerStateData {
@@ -77,16 +271,209 @@ impl CameraWorker {
                 state: CameraWorkerState::Disconnected,
             },
             imagepath_tx,
+            cameras_tx,
             camera_context: CameraContext::new()?,
-            camera: None,
+            cameras: Vec::new(),
+            next_camera_id: 0,
+            timeouts: CaptureTimeouts::default(),
         })
     }
 
+    fn publish_cameras(&self) {
+        let cameras = self.cameras.iter().map(|(info, _)| info.clone()).collect();
+        let _ = self.cameras_tx.send(cameras);
+    }
+
+    /// Publish the connected cameras, and the idle state that goes with them.
+    fn cameras_changed(&mut self) {
+        self.publish_cameras();
+        self.state.update(match self.cameras.is_empty() {
+            true => CameraWorkerState::Disconnected,
+            false => CameraWorkerState::Ready,
+        });
+    }
+
     fn generate_temp_image_path(&self) -> PathBuf {
         let filename = format!("image_{}", Uuid::new_v4());
         env::temp_dir().join(filename)
     }
 
+    /// Download a captured image to a temporary file, returning its path.
+    async fn download_image(
+        &self,
+        camera: &Camera,
+        camera_path: &CameraFilePath,
+    ) -> anyhow::Result<PathBuf> {
+        let image_path = self.generate_temp_image_path();
+        let camera_file = camera
+            .download(camera_path, &image_path, self.timeouts.download)
+            .await?;
+        // Rename output file with appropriate extension, if available
+        let new_path = match mime2ext(camera_file.mime_type()) {
+            Some(ext) => {
+                let path_with_ext = image_path.with_extension(ext);
+                fs::rename(image_path, &path_with_ext).await?;
+                path_with_ext
+            }
+            None => image_path,
+        };
+        Ok(new_path)
+    }
+
+    /// Capture and download every frame for one `seq`, publishing Capturing and Downloading states for each.
+    /// Fails if the camera couldn't take a picture, or a picture couldn't be downloaded.
+    async fn capture_frames(
+        &self,
+        (info, camera): &(ConnectedCamera, Camera),
+        job: Option<u32>,
+        seq: u32,
+        pose: Option<Pose>,
+        background: bool,
+        frames: Option<&FrameSet>,
+    ) -> anyhow::Result<CaptureReport> {
+        let settings = match frames {
+            Some(frames) => frames.settings().into_iter().map(Some).collect(),
+            None => vec![None],
+        };
+        let set_compensation = matches!(
+            frames,
+            Some(FrameSet::Bracket(bracket)) if bracket.method == BracketMethod::ConfigChange
+        );
+        let focus_drive = match frames {
+            Some(FrameSet::FocusStack(stack)) => stack.drive,
+            _ => 0,
+        };
+        // Camera changes to undo once the frames are done
+        let mut original_compensation = None;
+        let mut focus_drives = 0;
+        let mut report = CaptureReport::default();
+        let mut result = Ok(());
+        for (shot, setting) in settings.into_iter().enumerate() {
+            self.state.publish(CameraWorkerState::Capturing { seq });
+            let capture_start = Instant::now();
+            let captured = async {
+                match setting {
+                    Some(FrameSetting::ExposureStops(stops)) if set_compensation => {
+                        let previous = camera.set_exposure_compensation(stops).await?;
+                        original_compensation.get_or_insert(previous);
+                    }
+                    Some(FrameSetting::FocusStep(step)) if step > 0 => {
+                        camera.drive_focus(focus_drive).await?;
+                        focus_drives += 1;
+                        sleep(FOCUS_DRIVE_SETTLE).await;
+                    }
+                    _ => {}
+                }
+                camera.capture(self.timeouts.capture).await
+            }
+            .await;
+            report.capture_time += capture_start.elapsed();
+            let camera_path = match captured {
+                Ok(camera_path) => camera_path,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            self.state.publish(CameraWorkerState::Downloading { seq });
+            let download_start = Instant::now();
+            let downloaded = self.download_image(camera, &camera_path).await;
+            report.download_time += download_start.elapsed();
+            match downloaded {
+                Ok(path) => {
+                    eprintln!("Wrote image to {:?}", path);
+                    let handle = ImageHandle {
+                        job,
+                        seq,
+                        camera: info.id,
+                        shot: shot as u16,
+                        setting,
+                        pose,
+                        background,
+                        path,
+                    };
+                    let _ = self.imagepath_tx.send(handle.clone());
+                    report.images.push(handle);
+                }
+                // A hung camera won't manage the next frame either
+                Err(e) if e.is::<CameraTimeout>() => {
+                    result = Err(e);
+                    break;
+                }
+                Err(e) => {
+                    result = Err(e.context(DownloadFailed));
+                    break;
+                }
+            }
+        }
+        if let Some(original) = original_compensation {
+            if let Err(e) = camera.restore_exposure_compensation(&original).await {
+                eprintln!("Failed to restore exposure compensation: {:?}", e);
+            }
+        }
+        for _ in 0..focus_drives {
+            if let Err(e) = camera.drive_focus(-focus_drive).await {
+                eprintln!("Failed to drive focus back: {:?}", e);
+                break;
+            }
+            sleep(FOCUS_DRIVE_SETTLE).await;
+        }
+        result.map(|()| report)
+    }
+
+    /// Capture on each camera the trigger selects. Every camera gets to finish, even if another fails.
+    async fn capture_all(
+        &self,
+        job: Option<u32>,
+        seq: u32,
+        pose: Option<Pose>,
+        background: bool,
+        frames: Option<&FrameSet>,
+        trigger: CameraTrigger,
+    ) -> Result<CaptureReport, CaptureError> {
+        let cameras = match trigger {
+            CameraTrigger::Primary => &self.cameras[..1],
+            CameraTrigger::Sequential => &self.cameras[..],
+        };
+        let mut results = Vec::new();
+        for camera in cameras {
+            results.push(
+                self.capture_frames(camera, job, seq, pose, background, frames)
+                    .await,
+            );
+        }
+        let mut report = CaptureReport::default();
+        let mut failed = Vec::new();
+        let mut timed_out = Vec::new();
+        let mut download_failed = false;
+        for ((info, _), result) in cameras.iter().zip(results) {
+            match result {
+                Ok(camera_report) => report.combine(camera_report),
+                Err(e) => {
+                    eprintln!("Failed to capture image from {}: {:?}", info.id, e);
+                    if e.is::<CameraTimeout>() {
+                        timed_out.push(info.id);
+                    }
+                    download_failed |= e.is::<DownloadFailed>();
+                    failed.push(format!("{}: {:#}", info.id, e));
+                }
+            }
+        }
+        let message = failed.join(", ");
+        match (failed.is_empty(), timed_out.is_empty(), download_failed) {
+            (true, ..) if report.images.is_empty() => Err(CaptureError::Download(
+                "No images were downloaded".to_string(),
+            )),
+            (true, ..) => Ok(report),
+            (false, false, _) => Err(CaptureError::TimedOut {
+                cameras: timed_out,
+                message,
+            }),
+            (false, true, true) => Err(CaptureError::Download(message)),
+            (false, true, false) => Err(CaptureError::Capture(message)),
+        }
+    }
+
     pub(crate) async fn run(mut self) {
         self.state.update(CameraWorkerState::Disconnected);
         while let Some(cmd) = self.state.cmd_rx.recv().await {
@@ -107,79 +494,149 @@ impl CameraWorker {
                     };
                 }
                 CameraWorkerCommand::ConnectToCamera { camera_spec } => {
+                    if self
+                        .cameras
+                        .iter()
+                        .any(|(info, _)| info.spec == camera_spec)
+                    {
+                        eprintln!("Camera {} is already connected", camera_spec.name());
+                        continue;
+                    }
                     self.state.update(CameraWorkerState::CameraConnecting);
-                    match camera_spec.connect(&self.camera_context) {
+                    match camera_spec
+                        .connect(&self.camera_context, CONNECT_TIMEOUT)
+                        .await
+                    {
                         Ok(camera) => {
-                            self.camera = Some(camera);
-                            self.state.update(CameraWorkerState::Ready);
+                            let id = CameraId(self.next_camera_id);
+                            self.next_camera_id += 1;
+                            self.cameras.push((
+                                ConnectedCamera {
+                                    id,
+                                    spec: camera_spec,
+                                    responding: true,
+                                },
+                                camera,
+                            ));
                         }
                         Err(e) => {
                             eprintln!("Error connecting to camera {}: {:?}", camera_spec.name(), e);
-                            self.state.update(CameraWorkerState::Disconnected);
                         }
                     }
+                    self.cameras_changed();
                 }
                 CameraWorkerCommand::Disconnect => {
-                    self.state.update(CameraWorkerState::Disconnected);
+                    self.cameras.clear();
+                    self.cameras_changed();
+                }
+                CameraWorkerCommand::DisconnectCamera { id } => {
+                    self.cameras.retain(|(info, _)| info.id != id);
+                    self.cameras_changed();
+                }
+                CameraWorkerCommand::ReconnectCamera { id } => {
+                    let Some((info, camera)) =
+                        self.cameras.iter_mut().find(|(info, _)| info.id == id)
+                    else {
+                        continue;
+                    };
+                    if camera.is_stuck() {
+                        eprintln!(
+                            "Not reconnecting {}: an operation that timed out is still running",
+                            id
+                        );
+                        continue;
+                    }
+                    self.state.update(CameraWorkerState::CameraConnecting);
+                    match info
+                        .spec
+                        .connect(&self.camera_context, CONNECT_TIMEOUT)
+                        .await
+                    {
+                        Ok(reconnected) => {
+                            *camera = reconnected;
+                            info.responding = true;
+                        }
+                        Err(e) => {
+                            eprintln!("Error reconnecting to {}: {:?}", id, e);
+                        }
+                    }
+                    self.cameras_changed();
+                }
+                CameraWorkerCommand::SetTimeouts { timeouts } => {
+                    self.timeouts = timeouts;
                 }
                 CameraWorkerCommand::CaptureImage {
+                    job,
                     seq,
-                    extra_delay_ms,
+                    pose,
+                    background,
+                    frames,
+                    trigger,
+                    reply,
                 } => {
-                    match (&self.state.state, &self.camera) {
-                        (CameraWorkerState::Ready, Some(camera)) => {
-                            self.state.update(CameraWorkerState::Capturing { seq });
-                            let image_path = self.generate_temp_image_path();
-                            sleep(Duration::from_millis(extra_delay_ms)).await;
-                            match camera.capture(&image_path).await {
-                                Ok(camera_file) => {
-                                    match async || -> anyhow::Result<PathBuf> {
-                                        // Rename output file with appropriate extension, if available
-                                        let new_path = match mime2ext(camera_file.mime_type()) {
-                                            Some(ext) => {
-                                                let path_with_ext = image_path.with_extension(ext);
-                                                fs::rename(image_path, &path_with_ext).await?;
-                                                path_with_ext
-                                            }
-                                            None => image_path,
-                                        };
-                                        Ok(new_path)
-                                    }()
-                                    .await
-                                    {
-                                        Ok(path) => {
-                                            eprintln!("Wrote image to {:?}", path);
-                                            self.state.update(CameraWorkerState::Ready);
-                                            let _ =
-                                                self.imagepath_tx.send(ImageHandle { seq, path });
-                                        }
-                                        Err(e) => {
-                                            eprintln!("Failed to download image: {:?}", e);
-                                            self.state.update(CameraWorkerState::Ready);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    eprintln!("Failed to capture image from camera: {:?}", e);
-                                    self.state.update(CameraWorkerState::Failed);
-                                }
-                            }
-                        }
-                        (CameraWorkerState::Failed, Some(_)) => {
-                            eprintln!("Requested image capture and we have a camera, but state is still Failed");
-                            // Turntable worker waits for Capturing state as an acknowledgement.
-                            self.state.update(CameraWorkerState::Capturing { seq });
-                            self.state.update(CameraWorkerState::Failed);
+                    let result = match (&self.state.state, self.cameras.is_empty()) {
+                        (_, true) => Err(CaptureError::NoCamera),
+                        // Listing cameras to add another leaves the connected ones ready
+                        (CameraWorkerState::Ready | CameraWorkerState::CamerasListed { .. }, _) => {
+                            let result = self
+                                .capture_all(job, seq, pose, background, frames.as_ref(), trigger)
+                                .await;
+                            self.state.update(match result {
+                                Ok(_) => CameraWorkerState::Ready,
+                                Err(_) => CameraWorkerState::Failed,
+                            });
+                            result
                         }
-                        (other, _) => {
-                            eprintln!("Requested image capture, but no camera is connected!");
-                            let new = other.clone();
-                            self.state.update(CameraWorkerState::Capturing { seq });
-                            self.state.update(new);
+                        (CameraWorkerState::Failed, _) => Err(CaptureError::CameraFailed),
+                        _ => Err(CaptureError::Busy),
+                    };
+                    if let Err(e) = &result {
+                        eprintln!("Requested image capture for {}: {}", seq, e);
+                    }
+                    if let Err(CaptureError::TimedOut { cameras, .. }) = &result {
+                        for (info, _) in &mut self.cameras {
+                            if cameras.contains(&info.id) {
+                                info.responding = false;
+                            }
                         }
+                        self.publish_cameras();
+                    }
+                    if let Some(reply) = reply {
+                        let _ = reply.send(result);
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(camera: u16, capture_secs: u64, download_secs: u64) -> CaptureReport {
+        CaptureReport {
+            images: vec![ImageHandle {
+                job: None,
+                seq: 0,
+                camera: CameraId(camera),
+                shot: 0,
+                setting: None,
+                pose: None,
+                background: false,
+                path: PathBuf::new(),
+            }],
+            capture_time: Duration::from_secs(capture_secs),
+            download_time: Duration::from_secs(download_secs),
+        }
+    }
+
+    #[test]
+    fn test_combine_reports() {
+        let mut combined = report(0, 2, 3);
+        combined.combine(report(1, 4, 1));
+        assert_eq!(combined.images.len(), 2);
+        assert_eq!(combined.capture_time, Duration::from_secs(6));
+        assert_eq!(combined.download_time, Duration::from_secs(4));
+    }
+}
@@ -1,11 +1,59 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use tokio::{
-    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    sync::{
+        mpsc::{UnboundedReceiver, UnboundedSender},
+        Semaphore,
+    },
     task::JoinSet,
 };
 
-use crate::app::{worker::worker_camera::ImageHandle, ImagePreview};
+use image::RgbaImage;
+use serde::Serialize;
+
+use crate::app::{
+    worker::worker_camera::{CameraId, ImageHandle},
+    ImagePreview,
+};
+use crate::camera::FrameSetting;
+use crate::mask::{self, ChromaKey, Keying, MaskOutput, MaskSettings};
+use crate::merge;
+
+/// Decode a JPEG at 1/8 scale, which turbojpeg does far faster than a full decode.
+pub(crate) fn load_thumbnail(path: &Path) -> anyhow::Result<RgbaImage> {
+    let jpeg_data = std::fs::read(path)?;
+
+    // initialize a decompressor with the scaling factor
+    let mut decompressor = turbojpeg::Decompressor::new()?;
+    let scaling = turbojpeg::ScalingFactor::ONE_EIGHTH;
+    decompressor.set_scaling_factor(scaling)?;
+
+    // read the JPEG header and downscale the width and height
+    let scaled_header = decompressor.read_header(&jpeg_data)?.scaled(scaling);
+
+    // initialize the image (Image<Vec<u8>>)
+    let mut image = turbojpeg::Image {
+        pixels: vec![0; 4 * scaled_header.width * scaled_header.height],
+        width: scaled_header.width,
+        pitch: 4 * scaled_header.width, // size of one image row in memory
+        height: scaled_header.height,
+        format: turbojpeg::PixelFormat::RGBA,
+    };
+
+    // decompress the JPEG into the image
+    // (we use as_deref_mut() to convert from &mut Image<Vec<u8>> into Image<&mut [u8]>)
+    decompressor.decompress(&jpeg_data, image.as_deref_mut())?;
+
+    RgbaImage::from_raw(image.width as u32, image.height as u32, image.pixels)
+        .ok_or(anyhow::anyhow!("Decoded image has the wrong size"))
+}
+
+/// Decode a JPEG at full size, for inspecting detail the thumbnails lose.
+pub(crate) fn load_image(path: &Path) -> anyhow::Result<RgbaImage> {
+    let jpeg_data = std::fs::read(path)?;
+    Ok(turbojpeg::decompress_image(&jpeg_data)?)
+}
 
 /// Image loader task.
 /// - `camera_imagepath_rx` delivers `ImageHandle`s.  
@@ -23,11 +71,10 @@ pub async fn image_loader(
     while let Some(handle) = camera_imagepath_rx.recv().await {
         let tx = image_tx.clone();
         // let ctx = ctx.clone();
-        let path = handle.path.clone();
 
         // Spawn blocking work for image decoding & resizing
         join_set.spawn_blocking(move || {
-            match ImagePreview::load(handle.seq, &path).and_then(|preview| {
+            match ImagePreview::load(&handle).and_then(|preview| {
                 tx.send(preview)
                     .map_err(|e| anyhow::anyhow!("Send error: {}", e))
             }) {
@@ -48,30 +95,312 @@ pub async fn image_loader(
     }
 }
 
+/// A captured image to export.
 #[derive(Debug, Clone)]
-pub struct ExportJob {
+pub struct ExportFrame {
     pub image_path: PathBuf,
+    pub shot: u16,
+    pub setting: Option<FrameSetting>,
+}
+
+/// File layout for masks, matching what photogrammetry tools look for.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MaskNaming {
+    /// `image_<pose>.mask.png` next to each image
+    #[default]
+    RealityCapture,
+    /// `masks/image_<pose>.png`, with the same name as the image, in a directory of their own
+    Meshroom,
+}
+
+impl MaskNaming {
+    pub const ALL: [MaskNaming; 2] = [MaskNaming::RealityCapture, MaskNaming::Meshroom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MaskNaming::RealityCapture => "RealityCapture",
+            MaskNaming::Meshroom => "Meshroom",
+        }
+    }
+}
+
+/// How the object in a pose's images is separated from its surroundings.
+#[derive(Debug, Clone)]
+pub enum MaskMethod {
+    /// Against a background plate, with its frames by shot to match the pose's frames
+    Plate {
+        plates: Vec<PathBuf>,
+        settings: MaskSettings,
+    },
+    /// By keying out a coloured backdrop
+    Chroma(ChromaKey),
+}
+
+/// Masks or cutouts to write for a pose.
+#[derive(Debug, Clone)]
+pub struct MaskJob {
+    pub method: MaskMethod,
+    pub output: MaskOutput,
+    /// Layout of masks. Cutouts always go in a subdirectory of their own
+    pub naming: MaskNaming,
+}
+
+/// Every image one camera captured at one pose, to export together.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    /// Turntable job that captured the frames, when each job's images go in their own subdirectory,
+    /// as every job numbers its poses from zero
+    pub capture_job: Option<u32>,
     pub seq: u32,
+    /// Camera that took the frames, when each camera's images go in their own subdirectory
+    pub camera: Option<CameraId>,
+    pub frames: Vec<ExportFrame>,
     pub output_directory: PathBuf,
+    /// Merge a bracket or focus stack into a single image, keeping the frames in a subdirectory
+    pub merge: bool,
+    /// Write an object mask or cutout alongside each image
+    pub masks: Option<MaskJob>,
+}
+
+/// Subdirectory holding the frames of merged brackets.
+const BRACKET_DIRECTORY: &str = "brackets";
+/// Subdirectory holding the frames of merged focus stacks.
+const STACK_DIRECTORY: &str = "stacks";
+/// Subdirectory holding masks named for Meshroom.
+const MASK_DIRECTORY: &str = "masks";
+/// Subdirectory holding cutouts.
+const CUTOUT_DIRECTORY: &str = "cutouts";
+
+/// Subdirectory holding a capture job's files, when more than one job is exported.
+pub fn job_directory(job: u32) -> String {
+    format!("job{}", job)
+}
+
+impl ExportJob {
+    /// Prefix a file name with the capture job's and camera's subdirectories, if they have them.
+    fn in_camera_directory(&self, name: String) -> String {
+        let name = match self.camera {
+            Some(camera) => format!("{}/{}", camera, name),
+            None => name,
+        };
+        match self.capture_job {
+            Some(job) => format!("{}/{}", job_directory(job), name),
+            None => name,
+        }
+    }
+
+    /// Path of the single image for this pose relative to the output directory, if there is one.
+    fn image_name(&self) -> Option<String> {
+        (self.frames.len() == 1 || self.merge)
+            .then(|| self.in_camera_directory(format!("image_{}.jpg", self.seq)))
+    }
+
+    /// Path of a frame relative to the output directory.
+    fn frame_name(&self, frame: &ExportFrame) -> String {
+        self.in_camera_directory(match (self.frames.len(), self.merge) {
+            (1, _) => format!("image_{}.jpg", self.seq),
+            (_, false) => format!("image_{}_{}.jpg", self.seq, frame.shot),
+            (_, true) => {
+                let directory = match frame.setting {
+                    Some(FrameSetting::FocusStep(_)) => STACK_DIRECTORY,
+                    _ => BRACKET_DIRECTORY,
+                };
+                format!("{}/image_{}_{}.jpg", directory, self.seq, frame.shot)
+            }
+        })
+    }
+
+    /// Images to mask, by path relative to the output directory, with the frame each mask is made from.
+    /// Merged images are masked using the middle frame, the metered exposure of a bracket, though
+    /// cutouts take their colour from the merged image.
+    fn masked_images(&self) -> Vec<(String, &ExportFrame)> {
+        match self.image_name() {
+            Some(image_name) => vec![(image_name, &self.frames[self.frames.len() / 2])],
+            None => self
+                .frames
+                .iter()
+                .map(|frame| (self.frame_name(frame), frame))
+                .collect(),
+        }
+    }
+
+    /// Path of the mask or cutout for an image, relative to the output directory.
+    fn mask_name(image_name: &str, masks: &MaskJob) -> String {
+        let image = Path::new(image_name);
+        let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+        let mask = match (masks.output, masks.naming) {
+            (MaskOutput::Cutout, _) => Path::new(CUTOUT_DIRECTORY).join(format!("{}.png", stem)),
+            (MaskOutput::Mask, MaskNaming::RealityCapture) => {
+                PathBuf::from(format!("{}.mask.png", stem))
+            }
+            (MaskOutput::Mask, MaskNaming::Meshroom) => {
+                Path::new(MASK_DIRECTORY).join(format!("{}.png", stem))
+            }
+        };
+        image
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(mask)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Mask or cutout files written for this pose, relative to the output directory.
+    fn mask_names(&self) -> Vec<String> {
+        match &self.masks {
+            Some(masks) => self
+                .masked_images()
+                .iter()
+                .map(|(image_name, _)| Self::mask_name(image_name, masks))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn export_masks(&self, masks: &MaskJob) -> anyhow::Result<()> {
+        let merged = self.frames.len() > 1 && self.merge;
+        for (image_name, frame) in self.masked_images() {
+            let keying = match &masks.method {
+                MaskMethod::Plate { plates, settings } => Keying::Plate {
+                    plate: plates
+                        .get(frame.shot as usize)
+                        .or(plates.first())
+                        .ok_or(anyhow::anyhow!("No background plate to mask against"))?
+                        .clone(),
+                    settings: *settings,
+                },
+                MaskMethod::Chroma(key) => Keying::Chroma(*key),
+            };
+            let dest_path = self
+                .output_directory
+                .join(Self::mask_name(&image_name, masks));
+            if let Some(directory) = dest_path.parent() {
+                std::fs::create_dir_all(directory)?;
+            }
+            let merged_path = merged.then(|| self.output_directory.join(&image_name));
+            mask::mask_file(
+                &frame.image_path,
+                merged_path.as_deref(),
+                &keying,
+                masks.output,
+                &dest_path,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn export(&self) -> anyhow::Result<()> {
+        for frame in &self.frames {
+            let dest_path = self.output_directory.join(self.frame_name(frame));
+            if let Some(directory) = dest_path.parent() {
+                std::fs::create_dir_all(directory)?;
+            }
+            std::fs::copy(&frame.image_path, &dest_path).map_err(|e| {
+                anyhow::anyhow!(
+                    "Couldn't copy {:?} to {:?}: {}",
+                    frame.image_path,
+                    dest_path,
+                    e
+                )
+            })?;
+        }
+        if let (true, Some(image_name)) = (self.frames.len() > 1, self.image_name()) {
+            let frames: Vec<_> = self
+                .frames
+                .iter()
+                .map(|frame| frame.image_path.as_path())
+                .collect();
+            let merge = match self.frames[0].setting {
+                Some(FrameSetting::FocusStep(_)) => merge::stack_focus,
+                _ => merge::fuse_exposures,
+            };
+            merge::merge_files(&frames, merge, &self.output_directory.join(image_name))?;
+        }
+        if let Some(masks) = &self.masks {
+            self.export_masks(masks)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct ManifestFrame {
+    file: String,
+    #[serde(flatten)]
+    setting: Option<FrameSetting>,
+}
+
+#[derive(Serialize)]
+struct ManifestPose {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job: Option<u32>,
+    seq: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    camera: Option<CameraId>,
+    /// Single or merged image for the pose
+    image: Option<String>,
+    frames: Vec<ManifestFrame>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    masks: Vec<String>,
+}
+
+/// JSON listing the files exported for each pose, grouping brackets and focus stacks together.
+pub fn export_manifest(jobs: &[ExportJob]) -> String {
+    let poses: Vec<_> = jobs
+        .iter()
+        .map(|job| ManifestPose {
+            job: job.capture_job,
+            seq: job.seq,
+            camera: job.camera,
+            image: job.image_name(),
+            frames: job
+                .frames
+                .iter()
+                .map(|frame| ManifestFrame {
+                    file: job.frame_name(frame),
+                    setting: frame.setting,
+                })
+                .collect(),
+            masks: job.mask_names(),
+        })
+        .collect();
+    serde_json::to_string_pretty(&serde_json::json!({ "poses": poses }))
+        .expect("Manifest serialization can't fail")
 }
 
-pub async fn image_exporter(mut job_rx: UnboundedReceiver<ExportJob>) {
+/// Export jobs as they arrive, sending a message to `failure_tx` for any which fail.
+///
+/// Merging holds every frame of a pose at full resolution, along with its pyramids, so only one job
+/// per core runs at a time to keep memory use bounded.
+pub async fn image_exporter(
+    mut job_rx: UnboundedReceiver<ExportJob>,
+    failure_tx: UnboundedSender<String>,
+) {
     let mut join_set: JoinSet<()> = JoinSet::new();
+    let slots = Arc::new(Semaphore::new(
+        std::thread::available_parallelism().map_or(2, |cores| cores.get()),
+    ));
 
     while let Some(job) = job_rx.recv().await {
+        let slot = slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Export semaphore is never closed");
+        let failure_tx = failure_tx.clone();
         join_set.spawn_blocking(move || {
-            let dest_path = job
-                .output_directory
-                .join(format!("image_{}", job.seq))
-                .with_extension("jpg");
-            match std::fs::copy(&job.image_path, &dest_path) {
-                Ok(_) => {}
-                Err(e) => println!(
-                    "Something went wrong trying to copy {:?} to {:?}: {:?}",
-                    job.image_path, dest_path, e
-                ),
-            };
+            if let Err(e) = job.export() {
+                eprintln!("Something went wrong exporting image {}: {:?}", job.seq, e);
+                let _ = failure_tx.send(format!("Couldn't export pose {}: {}", job.seq, e));
+            }
+            drop(slot);
         });
+        // Finished tasks are dropped as we go, rather than all held until the channel closes
+        while let Some(join_res) = join_set.try_join_next() {
+            if let Err(join_err) = join_res {
+                eprintln!("Image export task failed: {:?}", join_err);
+            }
+        }
     }
 
     // Wait for all remaining tasks to finish
@@ -82,3 +411,116 @@ pub async fn image_exporter(mut job_rx: UnboundedReceiver<ExportJob>) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(camera: Option<CameraId>, shots: u16, merge: bool) -> ExportJob {
+        ExportJob {
+            capture_job: None,
+            seq: 4,
+            camera,
+            frames: (0..shots)
+                .map(|shot| ExportFrame {
+                    image_path: PathBuf::new(),
+                    shot,
+                    setting: None,
+                })
+                .collect(),
+            output_directory: PathBuf::new(),
+            merge,
+            masks: None,
+        }
+    }
+
+    #[test]
+    fn test_file_names() {
+        let single = job(None, 1, true);
+        assert_eq!(single.image_name().as_deref(), Some("image_4.jpg"));
+        assert_eq!(single.frame_name(&single.frames[0]), "image_4.jpg");
+
+        let unmerged = job(None, 3, false);
+        assert_eq!(unmerged.image_name(), None);
+        assert_eq!(unmerged.frame_name(&unmerged.frames[2]), "image_4_2.jpg");
+
+        let merged = job(Some(CameraId(1)), 3, true);
+        assert_eq!(merged.image_name().as_deref(), Some("cam1/image_4.jpg"));
+        assert_eq!(
+            merged.frame_name(&merged.frames[1]),
+            "cam1/brackets/image_4_1.jpg"
+        );
+
+        let mut stack = job(None, 3, true);
+        stack.frames[1].setting = Some(FrameSetting::FocusStep(1));
+        assert_eq!(stack.frame_name(&stack.frames[1]), "stacks/image_4_1.jpg");
+    }
+
+    #[test]
+    fn test_export_jobs_sharing_seqs() {
+        let dir = std::env::temp_dir().join(format!("export-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let jobs: Vec<_> = (0..2)
+            .map(|capture_job| {
+                let image_path = dir.join(format!("capture_{}", capture_job));
+                std::fs::write(&image_path, format!("job {}", capture_job)).unwrap();
+                let mut export = job(None, 1, false);
+                export.capture_job = Some(capture_job);
+                export.seq = 0;
+                export.frames[0].image_path = image_path;
+                export.output_directory = dir.join("out");
+                export
+            })
+            .collect();
+        for export in &jobs {
+            export.export().unwrap();
+        }
+        for capture_job in 0..2 {
+            let exported = dir.join(format!("out/job{}/image_0.jpg", capture_job));
+            assert_eq!(
+                std::fs::read_to_string(exported).unwrap(),
+                format!("job {}", capture_job)
+            );
+        }
+        let manifest: serde_json::Value = serde_json::from_str(&export_manifest(&jobs)).unwrap();
+        assert_eq!(manifest["poses"][0]["image"], "job0/image_0.jpg");
+        assert_eq!(manifest["poses"][1]["job"], 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mask_names() {
+        let masked = |camera, shots, merge, output, naming| ExportJob {
+            masks: Some(MaskJob {
+                method: MaskMethod::Chroma(ChromaKey::default()),
+                output,
+                naming,
+            }),
+            ..job(camera, shots, merge)
+        };
+        let (mask, cutout) = (MaskOutput::Mask, MaskOutput::Cutout);
+        assert_eq!(
+            masked(None, 1, false, mask, MaskNaming::RealityCapture).mask_names(),
+            ["image_4.mask.png"]
+        );
+        assert_eq!(
+            masked(Some(CameraId(1)), 3, true, mask, MaskNaming::Meshroom).mask_names(),
+            ["cam1/masks/image_4.png"]
+        );
+        assert_eq!(
+            masked(None, 2, false, mask, MaskNaming::Meshroom).mask_names(),
+            ["masks/image_4_0.png", "masks/image_4_1.png"]
+        );
+        assert_eq!(
+            masked(
+                Some(CameraId(0)),
+                1,
+                false,
+                cutout,
+                MaskNaming::RealityCapture
+            )
+            .mask_names(),
+            ["cam0/cutouts/image_4.png"]
+        );
+    }
+}
@@ -1,54 +1,522 @@
 use crate::{
-    app::worker::worker_camera::{CameraWorkerCommand, CameraWorkerState},
-    turntable::Turntable,
+    app::worker::{
+        job_timing::{JobTiming, Phase, PoseTiming},
+        worker_camera::{
+            CameraId, CameraTrigger, CameraWorkerCommand, CaptureTimeouts, ConnectedCamera,
+            ImageHandle,
+        },
+        worker_image_loader::load_thumbnail,
+    },
+    camera::FrameSet,
+    quality,
+    turntable::{
+        calibration::{Axis, AxisTiming, MotionTiming},
+        DriverSettings, MotionSpeeds, Pose, TrafficKind, TrafficLog, Turntable,
+    },
 };
 use anyhow::anyhow;
+use image::RgbaImage;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{
-    broadcast,
     mpsc::{UnboundedReceiver, UnboundedSender},
+    oneshot, watch, Notify,
 };
+use tokio::time::{sleep, timeout, Instant};
+
+/// Relative moves timed for each axis during calibration. Sizes must differ to fit a per-degree term,
+/// and tilt moves are chosen to stay within the table's range.
+const ROTATION_CALIBRATION_MOVES: [f32; 4] = [10.0, -30.0, 90.0, -180.0];
+const TILT_CALIBRATION_MOVES: [f32; 4] = [5.0, -10.0, 20.0, -30.0];
+/// How often to query the table's angle while waiting for a calibration move to finish.
+const ANGLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Longest to wait for a calibration move to finish when polling the table's angle.
+const CALIBRATION_MOVE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Allowance per frame each camera captures at a pose, on top of the camera's capture and download
+/// timeouts, for changing exposure or driving focus between frames.
+/// The camera worker times out hung cameras itself, so waiting on its reply only catches the worker
+/// getting stuck.
+const CAPTURE_REPLY_MARGIN_PER_FRAME: Duration = Duration::from_secs(15);
+
+/// Order in which a job visits its poses.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum PoseOrdering {
+    /// Ring by ring, always rotating forwards, including one more step into each new ring.
+    /// A partial arc can't be continued forwards, so each ring starts from where the last ended.
+    #[default]
+    Continuous,
+    /// Ring by ring, reversing the rotation direction on alternate rings,
+    /// so moving between rings only tilts.
+    Serpentine,
+    /// Sweep through every tilt at each rotation, alternating the sweep direction.
+    TiltMajor,
+    /// Ring by ring, rotating back to the start of the ring before each tilt change.
+    ReturnHome,
+}
+
+impl PoseOrdering {
+    pub(crate) const ALL: [PoseOrdering; 4] = [
+        PoseOrdering::Continuous,
+        PoseOrdering::Serpentine,
+        PoseOrdering::TiltMajor,
+        PoseOrdering::ReturnHome,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            PoseOrdering::Continuous => "Continuous",
+            PoseOrdering::Serpentine => "Serpentine",
+            PoseOrdering::TiltMajor => "Tilt-major",
+            PoseOrdering::ReturnHome => "Return home between rings",
+        }
+    }
+}
+
+/// What to do when a capture shows the same view as the one before, despite the table being moved.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum MotionCheck {
+    #[default]
+    Off,
+    /// Note the pose as not moved, and carry on
+    Flag,
+    /// Stop the job, so the table can be homed and the pose captured again
+    Pause,
+}
+
+impl MotionCheck {
+    pub(crate) const ALL: [MotionCheck; 3] =
+        [MotionCheck::Off, MotionCheck::Flag, MotionCheck::Pause];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            MotionCheck::Off => "Off",
+            MotionCheck::Flag => "Flag",
+            MotionCheck::Pause => "Pause job",
+        }
+    }
+}
+
+/// Smallest rotation spacing accepted, to keep the number of poses sane.
+const MIN_ROTATION_SPACING_DEG: f32 = 0.1;
+
+/// Tolerance when comparing rotation angles, in degrees.
+const ANGLE_EPSILON_DEG: f32 = 1e-3;
+
+/// How a rotation range is divided up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RotationSpacing {
+    /// Evenly spaced positions, including both ends of a partial arc
+    Steps(u16),
+    /// Positions a fixed number of degrees apart, from the start of the arc
+    Degrees(f32),
+}
+
+/// Part of the rotation range sampled more densely than the rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DetailSector {
+    pub(crate) start_deg: f32,
+    pub(crate) end_deg: f32,
+    pub(crate) spacing_deg: f32,
+}
+
+/// Rotations visited on each tilt ring.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RotationArc {
+    /// Relative to the rotation the job started from
+    pub(crate) start_deg: f32,
+    /// A range of 360 degrees or more is a full circle, where the end position is the start
+    pub(crate) end_deg: f32,
+    pub(crate) spacing: RotationSpacing,
+    pub(crate) detail: Option<DetailSector>,
+}
+
+impl RotationArc {
+    /// Whether the arc goes all the way round, so its end is its start.
+    pub(crate) fn is_full_circle(&self) -> bool {
+        self.end_deg - self.start_deg >= 360.0
+    }
+
+    /// Rotation angles in increasing order.
+    pub(crate) fn angles(&self) -> Vec<f32> {
+        let span = (self.end_deg - self.start_deg).clamp(0.0, 360.0);
+        let full_circle = self.is_full_circle();
+        let end_deg = self.start_deg + span;
+        let spacing_deg = match self.spacing {
+            RotationSpacing::Steps(0 | 1) => return vec![self.start_deg],
+            RotationSpacing::Steps(steps) if full_circle => span / steps as f32,
+            RotationSpacing::Steps(steps) => span / (steps - 1) as f32,
+            RotationSpacing::Degrees(degrees) => degrees,
+        };
+        let mut angles = spaced_angles(self.start_deg, end_deg, spacing_deg);
+
+        if let Some(detail) = self.detail {
+            let detail_start_deg = detail.start_deg.max(self.start_deg);
+            let detail_end_deg = detail.end_deg.min(end_deg);
+            if detail_start_deg <= detail_end_deg {
+                angles.retain(|&angle| angle < detail_start_deg || angle > detail_end_deg);
+                angles.extend(spaced_angles(
+                    detail_start_deg,
+                    detail_end_deg,
+                    detail.spacing_deg,
+                ));
+                angles.sort_by(f32::total_cmp);
+                angles.dedup_by(|a, b| (*a - *b).abs() < ANGLE_EPSILON_DEG);
+            }
+        }
+
+        if full_circle {
+            // The end of a full circle is where it started
+            angles.retain(|&angle| angle < end_deg - ANGLE_EPSILON_DEG);
+        }
+        angles
+    }
+}
+
+/// Angles from `start_deg` to `end_deg` inclusive, `spacing_deg` apart.
+fn spaced_angles(start_deg: f32, end_deg: f32, spacing_deg: f32) -> Vec<f32> {
+    let spacing_deg = spacing_deg.max(MIN_ROTATION_SPACING_DEG);
+    let count = ((end_deg - start_deg) / spacing_deg + ANGLE_EPSILON_DEG).floor() as u32;
+    (0..=count)
+        .map(|i| start_deg + i as f32 * spacing_deg)
+        .collect()
+}
+
+/// How long to let the object stop wobbling before each capture.
+/// Each settle time is a fixed part plus an optional per-degree part, scaled by the size of the move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SettleTimes {
+    /// Before the first pose, after moving from the reset position
+    pub(crate) first_pose_ms: u64,
+    /// After a move that only rotates
+    pub(crate) rotation: AxisTiming,
+    /// After a move that tilts, which sets the object swaying for longer
+    pub(crate) tilt: AxisTiming,
+}
+
+impl SettleTimes {
+    /// Settle time after moving between two poses. Moves on both axes wait for the longer of the two.
+    fn after_move(&self, from: Pose, to: Pose) -> Duration {
+        let settle = |timing: &AxisTiming, degrees: f32| match degrees == 0.0 {
+            true => Duration::ZERO,
+            false => timing.estimate(degrees),
+        };
+        settle(&self.rotation, to.rotation_deg - from.rotation_deg)
+            .max(settle(&self.tilt, to.tilt_deg - from.tilt_deg))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct TurntableSteppingJob {
-    pub(crate) rotation_steps: u16,
+    pub(crate) rotation: RotationArc,
     pub(crate) tilt_lower: f32,
     pub(crate) tilt_upper: f32,
     pub(crate) tilt_steps: u16,
-    pub(crate) capture_delay_ms: u64,
+    pub(crate) ordering: PoseOrdering,
+    pub(crate) settle: SettleTimes,
+    /// Frames captured at each pose, in place of a single image
+    pub(crate) frames: Option<FrameSet>,
+    /// Cameras triggered at each pose. The next move waits for all of them to download
+    pub(crate) cameras: CameraTrigger,
+    /// Once every pose is captured, capture again any whose images are less sharp than this
+    /// fraction of the median for the job
+    pub(crate) recapture_below: Option<f32>,
+    /// Compare each capture with the previous one, to catch moves the table missed
+    pub(crate) motion_check: MotionCheck,
+    /// Capture background plates of the empty table, once per tilt ring at the start of the arc,
+    /// instead of the object
+    pub(crate) background: bool,
+    /// Applied when the job starts
+    pub(crate) speeds: MotionSpeeds,
+    /// What the camera worker allows each frame, to know how long to wait for its reply
+    pub(crate) camera_timeouts: CaptureTimeouts,
 }
 
 impl TurntableSteppingJob {
     fn tilt_step_size(&self) -> f32 {
-        (self.tilt_upper - self.tilt_lower) / (self.tilt_steps - 1) as f32
+        match self.tilt_steps {
+            0 | 1 => 0.0,
+            steps => (self.tilt_upper - self.tilt_lower) / (steps - 1) as f32,
+        }
+    }
+
+    /// Tilt ring and rotation position of every pose, in the order they're visited.
+    fn cells(&self) -> Vec<(usize, usize)> {
+        let rotations = 0..self.rotation.angles().len();
+        let tilts = 0..self.tilt_steps as usize;
+        if self.background {
+            return tilts.map(|t| (t, 0)).collect();
+        }
+        // A partial arc has no way forwards from its end, so rings alternate direction instead
+        let ordering = match self.ordering {
+            PoseOrdering::Continuous if !self.rotation.is_full_circle() => PoseOrdering::Serpentine,
+            ordering => ordering,
+        };
+        match ordering {
+            PoseOrdering::Continuous | PoseOrdering::ReturnHome => tilts
+                .flat_map(|t| rotations.clone().map(move |r| (t, r)))
+                .collect(),
+            PoseOrdering::Serpentine => tilts
+                .flat_map(|t| {
+                    let ring: Vec<_> = rotations.clone().map(|r| (t, r)).collect();
+                    match t % 2 {
+                        0 => ring,
+                        _ => ring.into_iter().rev().collect(),
+                    }
+                })
+                .collect(),
+            PoseOrdering::TiltMajor => rotations
+                .flat_map(|r| {
+                    let sweep: Vec<_> = tilts.clone().map(|t| (t, r)).collect();
+                    match r % 2 {
+                        0 => sweep,
+                        _ => sweep.into_iter().rev().collect(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn tilt_deg(&self, tilt_step: usize) -> f32 {
+        self.tilt_lower + tilt_step as f32 * self.tilt_step_size()
+    }
+
+    /// Every pose of the job in the order they're visited, relative to the rotation the job started from.
+    fn poses(&self) -> Vec<Pose> {
+        let angles = self.rotation.angles();
+        let full_circle = self.rotation.is_full_circle();
+        self.cells()
+            .into_iter()
+            .map(|(t, r)| {
+                // Continuous jobs around a full circle keep turning forwards into each new ring
+                let turns_deg = match self.ordering {
+                    PoseOrdering::Continuous if full_circle && !self.background => t as f32 * 360.0,
+                    _ => 0.0,
+                };
+                Pose {
+                    rotation_deg: turns_deg + angles[r],
+                    tilt_deg: self.tilt_deg(t),
+                }
+            })
+            .collect()
+    }
+
+    /// The job's poses laid out by tilt ring and rotation position.
+    pub(crate) fn grid(&self) -> PoseGrid {
+        let rotations_deg = match self.background {
+            true => vec![self.rotation.start_deg],
+            false => self.rotation.angles(),
+        };
+        PoseGrid {
+            tilts_deg: (0..self.tilt_steps as usize)
+                .map(|t| self.tilt_deg(t))
+                .collect(),
+            rotations_deg,
+            cells: self.cells(),
+        }
+    }
+
+    /// Time to wait before capturing the given step, after moving there from step `from`, or to the
+    /// first pose if `None`.
+    fn settle_time(&self, from: Option<u32>, step: u32) -> Duration {
+        match from {
+            None => Duration::from_millis(self.settle.first_pose_ms),
+            Some(previous) => {
+                let poses = self.poses();
+                self.settle
+                    .after_move(poses[previous as usize], poses[step as usize])
+            }
+        }
+    }
+
+    /// Estimated time spent moving over the whole job, starting from the origin,
+    /// with each axis moving in turn at the job's speeds.
+    pub(crate) fn estimated_move_time(&self) -> Duration {
+        let mut from = Pose::default();
+        let mut seconds = 0.0;
+        for to in self.poses() {
+            seconds += (to.rotation_deg - from.rotation_deg).abs()
+                / self.speeds.rotation_deg_per_sec
+                + (to.tilt_deg - from.tilt_deg).abs() / self.speeds.tilt_deg_per_sec;
+            from = to;
+        }
+        Duration::from_secs_f32(seconds)
     }
 }
 
+/// A job's poses as rows of tilt rings and columns of rotation positions.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PoseGrid {
+    /// Tilt of each ring, from the lowest
+    pub(crate) tilts_deg: Vec<f32>,
+    /// Rotation of each position around a ring, relative to where the job started
+    pub(crate) rotations_deg: Vec<f32>,
+    /// Ring and position of each step
+    pub(crate) cells: Vec<(usize, usize)>,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct TurntableSteppingState {
+    /// Numbers the worker's jobs in the order they started, to tell their images apart
+    id: u32,
     job: TurntableSteppingJob,
-    rotation_step: u16,
-    tilt_step: u16,
+    /// Index of the current pose in the job's ordering
+    step: u32,
+    /// Step the table moved from to reach the current one, which isn't the one before when revisiting
+    previous_step: Option<u32>,
+    /// Table rotation when the job was started
+    rotation_origin_deg: f32,
+    timing: JobTiming,
+    /// Why the job paused itself, when it wasn't paused by the user
+    pause_reason: Option<String>,
+    /// Sharpness of each image by step, camera and shot, if recapturing blurred poses
+    sharpness: Vec<(u32, (CameraId, u16), f32)>,
+    /// Steps left to capture again, once the first pass over every pose is done
+    revisits: Option<Vec<u32>>,
+    /// Steps whose capture matched the previous pose's, so the table probably didn't move
+    unmoved: Vec<u32>,
+    /// How much each capture differed from the previous pose's, where the table moved
+    moved_differences: Vec<f32>,
 }
 
 impl TurntableSteppingState {
+    /// Pose for the current step, relative to the rotation the job started from.
+    fn job_pose(&self) -> Pose {
+        self.job.poses()[self.step as usize]
+    }
+
+    /// Absolute table pose for the current step.
+    pub(crate) fn pose(&self) -> Pose {
+        let pose = self.job_pose();
+        Pose {
+            rotation_deg: self.rotation_origin_deg + pose.rotation_deg,
+            ..pose
+        }
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+
     pub(crate) fn overall_step(&self) -> u32 {
-        (self.job.rotation_steps as u32 * self.tilt_step as u32) + self.rotation_step as u32
+        self.step
     }
 
     pub(crate) fn total_steps(&self) -> u32 {
-        self.job.rotation_steps as u32 * self.job.tilt_steps as u32
+        self.job.poses().len() as u32
     }
 
     pub(crate) fn progress(&self) -> f32 {
-        (self.overall_step() + 1) as f32 / self.total_steps() as f32
+        match self.revisits {
+            Some(_) => 1.0,
+            None => (self.overall_step() + 1) as f32 / self.total_steps() as f32,
+        }
+    }
+
+    pub(crate) fn timing(&self) -> &JobTiming {
+        &self.timing
+    }
+
+    pub(crate) fn grid(&self) -> PoseGrid {
+        self.job.grid()
+    }
+
+    /// Whether the job captured plates of the empty table rather than the object.
+    pub(crate) fn is_background(&self) -> bool {
+        self.job.background
+    }
+
+    /// Whether the job has got past a step, so it should have images by now.
+    pub(crate) fn passed(&self, step: u32) -> bool {
+        self.revisits.is_some() || step < self.step
+    }
+
+    pub(crate) fn unmoved_steps(&self) -> &[u32] {
+        &self.unmoved
+    }
+
+    pub(crate) fn pause_reason(&self) -> Option<&str> {
+        self.pause_reason.as_deref()
+    }
+
+    /// Paused at this step because of `error`.
+    fn paused_by(&self, error: &anyhow::Error) -> TurntableWorkerState {
+        eprintln!("Pausing job: {:?}", error);
+        TurntableWorkerState::Paused(TurntableSteppingState {
+            pause_reason: Some(error.to_string()),
+            ..self.clone()
+        })
+    }
+
+    /// Pick the step to capture next, starting on revisits once every pose has been captured.
+    fn next_step(&mut self) -> Option<u32> {
+        if self.revisits.is_none() {
+            if self.step + 1 < self.total_steps() {
+                return Some(self.step + 1);
+            }
+            self.revisits = Some(self.blurred_steps());
+        }
+        self.revisits
+            .as_mut()
+            .and_then(|revisits| (!revisits.is_empty()).then(|| revisits.remove(0)))
+    }
+
+    /// Steps with an image much less sharp than its peers, in capture order.
+    fn blurred_steps(&self) -> Vec<u32> {
+        let Some(ratio) = self.job.recapture_below else {
+            return Vec::new();
+        };
+        let scores: Vec<_> = self
+            .sharpness
+            .iter()
+            .map(|&(_, peers, score)| (peers, score))
+            .collect();
+        let mut steps: Vec<u32> = self
+            .sharpness
+            .iter()
+            .zip(quality::below_median(&scores, ratio))
+            .filter(|(_, blurred)| *blurred)
+            .map(|(&(step, _, _), _)| step)
+            .collect();
+        steps.dedup();
+        steps
     }
 
-    fn done(&self) -> bool {
-        (self.tilt_step >= self.job.tilt_steps - 1)
-            && (self.rotation_step >= self.job.rotation_steps - 1)
+    /// Score the images captured at the current step, replacing any earlier scores for it.
+    fn score_images(&mut self, thumbnails: &[(&ImageHandle, RgbaImage)]) {
+        let step = self.step;
+        self.sharpness.retain(|&(scored, _, _)| scored != step);
+        for (image, thumbnail) in thumbnails {
+            self.sharpness.push((
+                step,
+                (image.camera, image.shot),
+                quality::sharpness(thumbnail),
+            ));
+        }
     }
 }
 
+/// How the end of each calibration move is detected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CompletionDetection {
+    /// Poll the table's angle until it stops changing. Only rotation can be detected this way,
+    /// so tilt moves still need confirming.
+    AngleQuery,
+    /// Wait for the user to confirm that the table has stopped moving.
+    UserConfirmation,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CalibrationProgress {
+    pub(crate) axis: Axis,
+    pub(crate) move_index: usize,
+    pub(crate) total_moves: usize,
+    /// Waiting for [`TurntableWorkerCommand::ConfirmMoveComplete`]
+    pub(crate) awaiting_confirmation: bool,
+    /// Fitted timing or error, once calibration has finished
+    pub(crate) result: Option<Result<MotionTiming, String>>,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum TurntableWorkerState {
     Uninitialised,
@@ -57,154 +525,500 @@ pub(crate) enum TurntableWorkerState {
     ReturningToResetPosition,
     Stepping(TurntableSteppingState),
     Paused(TurntableSteppingState),
+    Calibrating(CalibrationProgress),
+    RotatingContinuously { direction: i8 },
 }
 
 #[derive(Debug)]
 pub(crate) enum TurntableWorkerCommand {
-    Connect,
+    Connect {
+        settings: DriverSettings,
+    },
     Disconnect,
     ResetPosition,
-    Step { job: TurntableSteppingJob },
+    Step {
+        job: TurntableSteppingJob,
+    },
     ResumeStepping,
     PauseStepping,
+    Calibrate {
+        detection: CompletionDetection,
+    },
+    ConfirmMoveComplete,
+    CancelCalibration,
+    /// Rotate relative to the current position
+    JogRotation {
+        degrees: f32,
+    },
+    /// Tilt to an absolute angle, keeping the current rotation
+    JogTilt {
+        tilt_deg: f32,
+    },
+    /// Rotate continuously, left if `direction` is negative or right if positive
+    StartContinuousRotation {
+        direction: i8,
+    },
+    StopContinuousRotation,
+    /// Send a raw protocol message, from the console
+    SendRaw {
+        message: String,
+    },
 }
 
 /// Tokio worker for managing a Turntable instance
-pub(crate) struct TurntableWorker<T: Turntable> {
+pub(crate) struct TurntableWorker {
     cmd_rx: UnboundedReceiver<TurntableWorkerCommand>,
     state_tx: UnboundedSender<TurntableWorkerState>,
     camera_cmd_tx: UnboundedSender<CameraWorkerCommand>,
-    camera_state_rx: broadcast::Receiver<CameraWorkerState>,
-    table: Option<T>,
+    /// Cameras connected to the camera worker, which capture in turn
+    cameras_rx: watch::Receiver<Vec<ConnectedCamera>>,
+    /// Estimated table position, published after every command and step
+    pose_tx: watch::Sender<Option<Pose>>,
+    /// Protocol traffic for the console
+    traffic: TrafficLog,
+    /// Signalled to halt the table, interrupting whatever the worker is doing
+    estop: Arc<Notify>,
+    table: Option<Box<dyn Turntable>>,
+    /// Job pose and preview of the last capture the table was known to have moved for
+    last_capture: Option<(Pose, RgbaImage)>,
+    /// Differences between poses of the last job to check motion, to judge a new job's first
+    /// moves by until it has differences of its own
+    previous_differences: Vec<f32>,
+    next_job_id: u32,
+    /// Commands received while a calibration move was being timed, to handle once it's finished
+    deferred: VecDeque<TurntableWorkerCommand>,
 }
 
-impl<T: Turntable> TurntableWorker<T> {
+impl TurntableWorker {
     pub(crate) fn new(
         cmd_rx: UnboundedReceiver<TurntableWorkerCommand>,
         state_tx: UnboundedSender<TurntableWorkerState>,
         camera_cmd_tx: UnboundedSender<CameraWorkerCommand>,
-        camera_state_rx: broadcast::Receiver<CameraWorkerState>,
+        cameras_rx: watch::Receiver<Vec<ConnectedCamera>>,
+        pose_tx: watch::Sender<Option<Pose>>,
+        traffic: TrafficLog,
+        estop: Arc<Notify>,
     ) -> Self {
         Self {
             cmd_rx,
             state_tx,
             camera_cmd_tx,
-            camera_state_rx,
+            cameras_rx,
+            pose_tx,
+            traffic,
+            estop,
             table: None,
+            last_capture: None,
+            previous_differences: Vec::new(),
+            next_job_id: 0,
+            deferred: VecDeque::new(),
         }
     }
 
-    async fn zero_position(&mut self, tilt_lower: f32) -> anyhow::Result<()> {
+    fn publish_pose(&self) {
+        let _ = self
+            .pose_tx
+            .send(self.table.as_ref().and_then(|tbl| tbl.position()));
+    }
+
+    /// Halt the table after an emergency stop. Any job is left paused, to be re-homed when resumed.
+    async fn emergency_stop(&mut self, state: &TurntableWorkerState) -> TurntableWorkerState {
+        eprintln!("Emergency stop");
+        if let Some(tbl) = self.table.as_mut() {
+            if let Err(e) = tbl.stop().await {
+                eprintln!("Failed to stop turntable: {:?}", e);
+            }
+        }
+        match state {
+            TurntableWorkerState::Stepping(stepping_state)
+            | TurntableWorkerState::Paused(stepping_state) => {
+                TurntableWorkerState::Paused(stepping_state.clone())
+            }
+            TurntableWorkerState::Uninitialised | TurntableWorkerState::Connecting => {
+                TurntableWorkerState::Uninitialised
+            }
+            _ => TurntableWorkerState::Connected,
+        }
+    }
+
+    /// If the position was lost (e.g. by an emergency stop), re-home and return to a paused job's pose.
+    async fn recover_position(&mut self, state: &TurntableSteppingState) -> anyhow::Result<()> {
         let tbl = self.table.as_mut().ok_or(anyhow!("Table not present!"))?;
-        // Zero tilt
-        tbl.reset_tilt().await?;
-        // Tilt to lower tilt position
-        tbl.step_tilt(0.0, tilt_lower as f32).await?;
+        if tbl.position().is_none() {
+            tbl.reset_pos().await?;
+            let pose = state.pose();
+            tbl.move_to(pose.rotation_deg, pose.tilt_deg).await?;
+        }
         Ok(())
     }
 
-    /// Trigger taking a photo, and wait until it either succeeds or fails.
-    async fn sync_take_photo(&mut self, state: &TurntableSteppingState) -> anyhow::Result<()> {
+    /// Move to a pose derived from the current position.
+    async fn jog(&mut self, target: impl FnOnce(Pose) -> Pose) -> anyhow::Result<()> {
+        let tbl = self.table.as_mut().ok_or(anyhow!("Table not present!"))?;
+        let position = tbl
+            .position()
+            .ok_or(anyhow!("Turntable position unknown, reset position first"))?;
+        let pose = target(position);
+        if pose.tilt_deg.abs() > tbl.max_tilt_deg() {
+            return Err(anyhow!(
+                "Tilt of {} deg is beyond the table's limit of {} deg",
+                pose.tilt_deg,
+                tbl.max_tilt_deg()
+            ));
+        }
+        tbl.move_to(pose.rotation_deg, pose.tilt_deg).await
+    }
+
+    /// Move to the first pose of a job, returning the initial stepping state.
+    async fn move_to_start(
+        &mut self,
+        job: &TurntableSteppingJob,
+    ) -> anyhow::Result<TurntableSteppingState> {
+        let tbl = self.table.as_mut().ok_or(anyhow!("Table not present!"))?;
+        tbl.set_speeds(job.speeds).await?;
+        if tbl.position().is_none() {
+            tbl.reset_pos().await?;
+        }
+        let position = tbl
+            .position()
+            .ok_or(anyhow!("Turntable position unknown"))?;
+        let mut state = TurntableSteppingState {
+            id: self.next_job_id,
+            job: job.clone(),
+            step: 0,
+            previous_step: None,
+            rotation_origin_deg: position.rotation_deg,
+            timing: JobTiming::new(),
+            pause_reason: None,
+            sharpness: Vec::new(),
+            revisits: None,
+            unmoved: Vec::new(),
+            moved_differences: Vec::new(),
+        };
+        self.next_job_id += 1;
+        self.last_capture = None;
+        let pose = state.pose();
+        let start = Instant::now();
+        tbl.move_to(pose.rotation_deg, pose.tilt_deg).await?;
+        state.timing.record(0, Phase::Move, start.elapsed());
+        Ok(state)
+    }
+
+    /// Let the table settle, then ask the camera worker to take a photo and wait for its reply.
+    /// Returns the time spent settling, capturing and downloading, and the images captured.
+    async fn sync_take_photo(
+        &mut self,
+        state: &TurntableSteppingState,
+    ) -> anyhow::Result<(PoseTiming, Vec<ImageHandle>)> {
         let seq = state.overall_step();
-        // Drain any unhandled camera states (e.g. from manual captures)
-        while let Ok(_) = self.camera_state_rx.try_recv() {}
-        match self.camera_cmd_tx.send(CameraWorkerCommand::CaptureImage {
-            seq,
-            extra_delay_ms: state.job.capture_delay_ms,
-        }) {
-            Ok(_) => {
-                // Wait for camera worker state to first go to Capturing, then exit it
-                eprintln!("Waiting for camera Capturing...");
-                while match self.camera_state_rx.recv().await {
-                    Ok(CameraWorkerState::Capturing { seq: recvd_seq }) => {
-                        // If recvd_seq is our requested seq, break.
-                        recvd_seq != seq
-                    }
-                    _ => true, // keep looping
-                } {}
-                // Wait for camera worker state to exit Capturing
-                eprintln!("Waiting for camera exit Capturing...");
-                while match self.camera_state_rx.recv().await {
-                    Ok(CameraWorkerState::Capturing { seq: _ }) => true, // keep looping
-                    Ok(CameraWorkerState::Ready) => {
-                        // all done!
-                        return Ok(());
-                    }
-                    Ok(CameraWorkerState::Failed) => {
-                        return Err(anyhow!("Camera capture failed"));
-                    }
-                    _ => false,
-                } {}
-                Err(anyhow!("Something funny happened"))
+        let settle_start = Instant::now();
+        sleep(state.job.settle_time(state.previous_step, seq)).await;
+        let settle_time = settle_start.elapsed();
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.camera_cmd_tx
+            .send(CameraWorkerCommand::CaptureImage {
+                job: Some(state.id),
+                seq,
+                pose: Some(state.job_pose()),
+                background: state.job.background,
+                frames: state.job.frames.clone(),
+                trigger: state.job.cameras,
+                reply: Some(reply_tx),
+            })
+            .map_err(|_| anyhow!("Unable to send command to camera worker"))?;
+        let frame_count = state
+            .job
+            .frames
+            .as_ref()
+            .map_or(1, |frames| frames.settings().len());
+        let camera_count = match state.job.cameras {
+            CameraTrigger::Primary => 1,
+            CameraTrigger::Sequential => self.cameras_rx.borrow().len().max(1),
+        };
+        let timeouts = state.job.camera_timeouts;
+        let reply_timeout = (timeouts.capture + timeouts.download + CAPTURE_REPLY_MARGIN_PER_FRAME)
+            * (frame_count * camera_count) as u32;
+        let report = match timeout(reply_timeout, reply_rx).await {
+            Ok(Ok(result)) => result?,
+            Ok(Err(_)) => return Err(anyhow!("Camera worker dropped the capture request")),
+            Err(_) => {
+                return Err(anyhow!(
+                    "Camera didn't reply to capture within {:?}",
+                    reply_timeout
+                ))
             }
-            Err(_) => Err(anyhow!("Unable to send command to camera worker")),
-        }
+        };
+        eprintln!("Captured {} images for step {}", report.images.len(), seq);
+        let timing = PoseTiming {
+            settle_time,
+            capture_time: report.capture_time,
+            download_time: report.download_time,
+            ..PoseTiming::default()
+        };
+        Ok((timing, report.images))
     }
 
-    /// Step the turntable once. Always rotates, and will also tilt after each complete rotation.
+    /// Move the turntable to the pose for `step`.
     /// Returns the new state after the step has been completed.
     async fn step_once(
         &mut self,
         from_state: &TurntableSteppingState,
+        step: u32,
     ) -> anyhow::Result<TurntableSteppingState> {
         match self.table.as_mut() {
             Some(tbl) => {
-                tbl.step_horizontal(from_state.job.rotation_steps).await?;
-                let new_rotation_step = from_state.rotation_step + 1;
-                let (rotation_step, tilt_step) =
-                    match new_rotation_step % from_state.job.rotation_steps {
-                        0 => {
-                            // Time to also tilt step
-                            let old_tilt_pos = from_state.job.tilt_lower
-                                + (from_state.tilt_step as f32) * from_state.job.tilt_step_size();
-                            let new_tilt_pos = old_tilt_pos + from_state.job.tilt_step_size();
-                            eprintln!("Tilting from {} to {} degrees", old_tilt_pos, new_tilt_pos);
-                            tbl.step_tilt(old_tilt_pos, new_tilt_pos).await?;
-                            eprintln!("Done!");
-                            (0, from_state.tilt_step + 1)
-                        }
-                        step => (step, from_state.tilt_step),
-                    };
-
-                Ok(TurntableSteppingState {
-                    job: from_state.job.clone(),
-                    rotation_step,
-                    tilt_step,
-                })
+                let mut new_state = TurntableSteppingState {
+                    step,
+                    previous_step: Some(from_state.step),
+                    ..from_state.clone()
+                };
+                let pose = new_state.pose();
+                eprintln!("Moving to {:?}", pose);
+                let start = Instant::now();
+                tbl.move_to(pose.rotation_deg, pose.tilt_deg).await?;
+                new_state
+                    .timing
+                    .record(new_state.step, Phase::Move, start.elapsed());
+                Ok(new_state)
             }
             None => Err(anyhow!("Unable to reference turntable")),
         }
     }
 
+    /// Compare a step's first capture with the last one, in case the table missed the move between them.
+    /// Fails if the table didn't move and the job should pause, leaving the position unknown so that
+    /// resuming homes the table before returning to the pose.
+    async fn check_motion(
+        &mut self,
+        state: &mut TurntableSteppingState,
+        thumbnail: &RgbaImage,
+    ) -> anyhow::Result<()> {
+        if state.job.motion_check == MotionCheck::Off {
+            return Ok(());
+        }
+        let (step, pose) = (state.step, state.pose());
+        let difference = match &self.last_capture {
+            Some((previous_pose, previous)) => {
+                let moved = (pose.rotation_deg - previous_pose.rotation_deg).abs()
+                    > ANGLE_EPSILON_DEG
+                    || (pose.tilt_deg - previous_pose.tilt_deg).abs() > ANGLE_EPSILON_DEG;
+                moved.then(|| quality::difference(previous, thumbnail))
+            }
+            None => None,
+        };
+        let reference = match state.moved_differences.is_empty() {
+            true => &self.previous_differences,
+            false => &state.moved_differences,
+        };
+        let unmoved = difference.is_some_and(|difference| quality::unmoved(difference, reference));
+        state.unmoved.retain(|&flagged| flagged != step);
+        if !unmoved {
+            if let Some(difference) = difference {
+                state.moved_differences.push(difference);
+                self.previous_differences = state.moved_differences.clone();
+            }
+            self.last_capture = Some((pose, thumbnail.clone()));
+            return Ok(());
+        }
+        state.unmoved.push(step);
+        if state.job.motion_check == MotionCheck::Flag {
+            self.last_capture = Some((pose, thumbnail.clone()));
+            return Ok(());
+        }
+        if let Some(tbl) = self.table.as_mut() {
+            tbl.stop().await?;
+        }
+        Err(anyhow!(
+            "Table didn't move for step {}, it looks the same as the pose before. \
+             Resuming homes the table first",
+            step + 1
+        ))
+    }
+
     /// Attempts to capture an image, then step the turntable.
     /// After either success or failure, reports the new stepping state.
     async fn capture_step(
         &mut self,
         from_state: &TurntableSteppingState,
     ) -> Result<TurntableWorkerState, (TurntableWorkerState, anyhow::Error)> {
-        let next_state = match self.sync_take_photo(from_state).await {
-            Ok(_) => match self.step_once(from_state).await {
-                // Success. Report continued stepping with the new state after step
-                Ok(new_state) => Ok(TurntableWorkerState::Stepping(new_state)),
-                // Failed to step turntable. Report paused state
-                Err(e) => Err((TurntableWorkerState::Paused(from_state.clone()), e)),
-            },
-            Err(e) => {
-                // Failed to take photo. Report paused state
-                Err((TurntableWorkerState::Paused(from_state.clone()), e))
+        let (photo_timing, images) = match self.sync_take_photo(from_state).await {
+            Ok(captured) => captured,
+            // Failed to take photo. Report paused state
+            Err(e) => return Err((from_state.paused_by(&e), e)),
+        };
+        let mut captured_state = TurntableSteppingState {
+            pause_reason: None,
+            ..from_state.clone()
+        };
+        for phase in [Phase::Settle, Phase::Capture, Phase::Download] {
+            captured_state
+                .timing
+                .record(from_state.step, phase, photo_timing.phase(phase));
+        }
+        captured_state.timing.complete(from_state.step);
+        // Previews are only decoded for the checks that need them
+        let job = &captured_state.job;
+        let thumbnails: Vec<_> =
+            match job.recapture_below.is_some() || job.motion_check != MotionCheck::Off {
+                true => {
+                    // Decoding blocks, so it runs off the worker's thread where it can't hold up
+                    // an emergency stop
+                    let paths: Vec<_> = images.iter().map(|image| image.path.clone()).collect();
+                    let decoded = tokio::task::spawn_blocking(move || {
+                        paths
+                            .iter()
+                            .map(|path| load_thumbnail(path))
+                            .collect::<Vec<_>>()
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!("Preview decoding task failed: {:?}", e);
+                        Vec::new()
+                    });
+                    images
+                        .iter()
+                        .zip(decoded)
+                        .filter_map(|(image, thumbnail)| match thumbnail {
+                            Ok(thumbnail) => Some((image, thumbnail)),
+                            Err(e) => {
+                                eprintln!("Couldn't load preview of {:?}: {:?}", image.path, e);
+                                None
+                            }
+                        })
+                        .collect()
+                }
+                false => Vec::new(),
+            };
+        if captured_state.job.recapture_below.is_some() {
+            captured_state.score_images(&thumbnails);
+        }
+        if let Some((_, thumbnail)) = thumbnails.first() {
+            if let Err(e) = self.check_motion(&mut captured_state, thumbnail).await {
+                return Err((captured_state.paused_by(&e), e));
             }
+        }
+        // Report timings now, as the last pose has no move to follow it
+        let _ = self
+            .state_tx
+            .send(TurntableWorkerState::Stepping(captured_state.clone()));
+
+        let Some(next_step) = captured_state.next_step() else {
+            // That was the last pose, so there's nowhere further to move
+            return Ok(TurntableWorkerState::Connected);
         };
-        if let Ok(TurntableWorkerState::Stepping(_)) = &next_state {
-            if from_state.done() {
-                Ok(TurntableWorkerState::Connected)
-            } else {
-                next_state
+        match self.step_once(&captured_state, next_step).await {
+            // Success. Report continued stepping with the new state after step
+            Ok(new_state) => Ok(TurntableWorkerState::Stepping(new_state)),
+            // Failed to step turntable. Report paused state
+            Err(e) => Err((captured_state.paused_by(&e), e)),
+        }
+    }
+
+    /// Start a relative calibration move, and time how long it takes to complete.
+    async fn time_move(
+        &mut self,
+        degrees: f32,
+        detection: CompletionDetection,
+        progress: CalibrationProgress,
+    ) -> anyhow::Result<Duration> {
+        let tbl = self.table.as_mut().ok_or(anyhow!("Table not present!"))?;
+        let detection = match progress.axis {
+            Axis::Rotation => detection,
+            Axis::Tilt => CompletionDetection::UserConfirmation,
+        };
+        let _ = self
+            .state_tx
+            .send(TurntableWorkerState::Calibrating(CalibrationProgress {
+                awaiting_confirmation: detection == CompletionDetection::UserConfirmation,
+                ..progress
+            }));
+        match detection {
+            CompletionDetection::AngleQuery => {
+                let initial = tbl.query_angle().await?.ok_or(anyhow!(
+                    "No reply to angle query, confirm moves manually instead"
+                ))?;
+                let start = Instant::now();
+                tbl.start_move(progress.axis, degrees).await?;
+                // The move is complete once the angle has changed, then reads the same twice running
+                let (mut last_angle, mut last_change) = (initial, start);
+                loop {
+                    sleep(ANGLE_POLL_INTERVAL).await;
+                    match self.cmd_rx.try_recv() {
+                        Ok(TurntableWorkerCommand::CancelCalibration) => {
+                            return Err(anyhow!("Calibration cancelled"))
+                        }
+                        Ok(cmd) => defer_during_calibration(&mut self.deferred, cmd)?,
+                        Err(_) => {}
+                    }
+                    if start.elapsed() > CALIBRATION_MOVE_TIMEOUT {
+                        return Err(anyhow!("Timed out waiting for move to complete"));
+                    }
+                    match tbl.query_angle().await? {
+                        Some(angle) if angle != last_angle => {
+                            (last_angle, last_change) = (angle, Instant::now());
+                        }
+                        Some(angle) if angle != initial => return Ok(last_change - start),
+                        _ => {}
+                    }
+                }
+            }
+            CompletionDetection::UserConfirmation => {
+                let start = Instant::now();
+                tbl.start_move(progress.axis, degrees).await?;
+                loop {
+                    match self.cmd_rx.recv().await {
+                        Some(TurntableWorkerCommand::ConfirmMoveComplete) => {
+                            return Ok(start.elapsed())
+                        }
+                        Some(TurntableWorkerCommand::CancelCalibration) | None => {
+                            return Err(anyhow!("Calibration cancelled"))
+                        }
+                        Some(cmd) => defer_during_calibration(&mut self.deferred, cmd)?,
+                    }
+                }
             }
-        } else {
-            next_state
         }
     }
 
+    /// Time a series of known moves on each axis, and fit a timing model to them.
+    /// The fitted model is saved for the device, and the table is left at its reset position.
+    async fn calibrate(&mut self, detection: CompletionDetection) -> anyhow::Result<MotionTiming> {
+        let tbl = self.table.as_mut().ok_or(anyhow!("Table not present!"))?;
+        let mut timing = tbl
+            .timing()
+            .ok_or(anyhow!("This turntable doesn't need calibrating"))?;
+        // Timing is measured at the default speeds
+        tbl.configure().await?;
+        tbl.reset_pos().await?;
+        for (axis, moves) in [
+            (Axis::Rotation, ROTATION_CALIBRATION_MOVES),
+            (Axis::Tilt, TILT_CALIBRATION_MOVES),
+        ] {
+            let mut samples = Vec::new();
+            for (move_index, degrees) in moves.into_iter().enumerate() {
+                let progress = CalibrationProgress {
+                    axis,
+                    move_index,
+                    total_moves: moves.len(),
+                    awaiting_confirmation: false,
+                    result: None,
+                };
+                let duration = self.time_move(degrees, detection, progress).await?;
+                eprintln!("{:?} move of {} deg took {:?}", axis, degrees, duration);
+                samples.push((degrees, duration));
+            }
+            *timing.axis_mut(axis) = AxisTiming::fit(&samples).ok_or(anyhow!(
+                "Unable to fit {:?} timing to {:?}",
+                axis,
+                samples
+            ))?;
+        }
+        let tbl = self.table.as_mut().ok_or(anyhow!("Table not present!"))?;
+        tbl.set_timing(timing)?;
+        tbl.reset_pos().await?;
+        Ok(timing)
+    }
+
     /// Handle a worker command.
     /// Returns the new worker state after handling the command. May publish state updates while running.
     async fn handle_command(
@@ -213,9 +1027,9 @@ impl<T: Turntable> TurntableWorker<T> {
         cmd: &TurntableWorkerCommand,
     ) -> TurntableWorkerState {
         match cmd {
-            TurntableWorkerCommand::Connect => {
+            TurntableWorkerCommand::Connect { settings } => {
                 let _ = self.state_tx.send(TurntableWorkerState::Connecting);
-                match T::connect().await {
+                match settings.connect(self.traffic.clone()).await {
                     Ok(mut tbl) => match tbl.configure().await {
                         Ok(_) => {
                             self.table = Some(tbl);
@@ -234,6 +1048,10 @@ impl<T: Turntable> TurntableWorker<T> {
             }
             TurntableWorkerCommand::Disconnect => {
                 if let Some(tbl) = self.table.as_mut() {
+                    // Don't leave anything moving
+                    if let Err(e) = tbl.stop().await {
+                        eprintln!("Failed to stop turntable: {:?}", e);
+                    }
                     match tbl.disconnect().await {
                         Ok(_) => {}
                         Err(_) => {}
@@ -247,20 +1065,21 @@ impl<T: Turntable> TurntableWorker<T> {
                     let _ = self
                         .state_tx
                         .send(TurntableWorkerState::ReturningToResetPosition);
-                    tbl.reset_pos().await.unwrap();
+                    if let Err(e) = tbl.reset_pos().await {
+                        eprintln!("Failed to reset position: {:?}", e);
+                    }
                 }
                 TurntableWorkerState::Connected
             }
             TurntableWorkerCommand::Step { job } => {
                 if let TurntableWorkerState::Connected = state {
                     // Set initial position and request to start stepping
-                    match self.zero_position(job.tilt_lower).await {
-                        Ok(_) => TurntableWorkerState::Stepping(TurntableSteppingState {
-                            job: job.clone(),
-                            rotation_step: 0,
-                            tilt_step: 0,
-                        }),
-                        Err(_) => state.clone(),
+                    match self.move_to_start(job).await {
+                        Ok(stepping_state) => TurntableWorkerState::Stepping(stepping_state),
+                        Err(e) => {
+                            eprintln!("Failed to move to start position: {:?}", e);
+                            state.clone()
+                        }
                     }
                 } else {
                     state.clone()
@@ -268,10 +1087,14 @@ impl<T: Turntable> TurntableWorker<T> {
             }
             TurntableWorkerCommand::ResumeStepping => {
                 if let TurntableWorkerState::Paused(stepping_state) = state {
+                    if let Err(e) = self.recover_position(stepping_state).await {
+                        eprintln!("Failed to return to paused position: {:?}", e);
+                        return state.clone();
+                    }
                     // Resume stepping from the saved state
                     match self.capture_step(&stepping_state).await {
                         Ok(new_state) => new_state,
-                        Err(_) => state.clone(),
+                        Err((new_state, _)) => new_state,
                     }
                 } else {
                     state.clone()
@@ -284,6 +1107,101 @@ impl<T: Turntable> TurntableWorker<T> {
                     state.clone()
                 }
             }
+            TurntableWorkerCommand::Calibrate { detection } => {
+                if let TurntableWorkerState::Connected = state {
+                    let result = self.calibrate(*detection).await;
+                    if let Err(e) = &result {
+                        eprintln!("Calibration failed: {:?}", e);
+                        // The table may have been left mid-move
+                        if let Some(tbl) = self.table.as_mut() {
+                            let _ = tbl.stop().await;
+                        }
+                    }
+                    // Report the result before returning to the connected state
+                    let _ = self.state_tx.send(TurntableWorkerState::Calibrating(
+                        CalibrationProgress {
+                            axis: Axis::Tilt,
+                            move_index: TILT_CALIBRATION_MOVES.len(),
+                            total_moves: TILT_CALIBRATION_MOVES.len(),
+                            awaiting_confirmation: false,
+                            result: Some(result.map_err(|e| e.to_string())),
+                        },
+                    ));
+                    TurntableWorkerState::Connected
+                } else {
+                    state.clone()
+                }
+            }
+            // Only meaningful while calibrating, where they're handled directly
+            TurntableWorkerCommand::ConfirmMoveComplete
+            | TurntableWorkerCommand::CancelCalibration => state.clone(),
+            TurntableWorkerCommand::JogRotation { degrees } => {
+                if let TurntableWorkerState::Connected = state {
+                    let result = self
+                        .jog(|pose| Pose {
+                            rotation_deg: pose.rotation_deg + degrees,
+                            ..pose
+                        })
+                        .await;
+                    if let Err(e) = result {
+                        eprintln!("Failed to jog rotation: {:?}", e);
+                    }
+                }
+                state.clone()
+            }
+            TurntableWorkerCommand::JogTilt { tilt_deg } => {
+                if let TurntableWorkerState::Connected = state {
+                    let result = self
+                        .jog(|pose| Pose {
+                            tilt_deg: *tilt_deg,
+                            ..pose
+                        })
+                        .await;
+                    if let Err(e) = result {
+                        eprintln!("Failed to jog tilt: {:?}", e);
+                    }
+                }
+                state.clone()
+            }
+            TurntableWorkerCommand::StartContinuousRotation { direction } => {
+                match (state, self.table.as_mut()) {
+                    (TurntableWorkerState::Connected, Some(tbl)) => {
+                        match tbl.start_continuous_rotation(*direction).await {
+                            Ok(_) => TurntableWorkerState::RotatingContinuously {
+                                direction: *direction,
+                            },
+                            Err(e) => {
+                                eprintln!("Failed to start rotating: {:?}", e);
+                                state.clone()
+                            }
+                        }
+                    }
+                    _ => state.clone(),
+                }
+            }
+            TurntableWorkerCommand::StopContinuousRotation => match (state, self.table.as_mut()) {
+                (TurntableWorkerState::RotatingContinuously { .. }, Some(tbl)) => {
+                    if let Err(e) = tbl.stop_continuous_rotation().await {
+                        eprintln!("Failed to stop rotating, halting: {:?}", e);
+                        let _ = tbl.stop().await;
+                    }
+                    TurntableWorkerState::Connected
+                }
+                _ => state.clone(),
+            },
+            TurntableWorkerCommand::SendRaw { message } => {
+                match (state, self.table.as_mut()) {
+                    (TurntableWorkerState::Connected, Some(tbl)) => {
+                        if let Err(e) = tbl.send_raw(message).await {
+                            self.traffic.record(TrafficKind::Error, &e.to_string());
+                        }
+                    }
+                    _ => self
+                        .traffic
+                        .record(TrafficKind::Error, "Turntable busy or not connected"),
+                }
+                state.clone()
+            }
         }
     }
 
@@ -291,10 +1209,37 @@ impl<T: Turntable> TurntableWorker<T> {
         let mut state = TurntableWorkerState::Uninitialised;
         let _ = self.state_tx.send(state.clone());
 
-        while let Some(cmd) = &self.cmd_rx.recv().await {
+        loop {
+            // Emergency stops are handled as soon as they're signalled, interrupting any command or step
+            let estop = self.estop.clone();
+            let cmd = if let Some(cmd) = self.deferred.pop_front() {
+                cmd
+            } else {
+                tokio::select! {
+                    cmd = self.cmd_rx.recv() => match cmd {
+                    Some(cmd) => cmd,
+                    None => break,
+                },
+                _ = estop.notified() => {
+                    state = self.emergency_stop(&state).await;
+                    let _ = self.state_tx.send(state.clone());
+                    self.publish_pose();
+                    continue;
+                }
+                }
+            };
+
             // Handle the command, updating state
-            state = self.handle_command(&mut state, cmd).await;
+            let handled = tokio::select! {
+                new_state = self.handle_command(&state, &cmd) => Some(new_state),
+                _ = estop.notified() => None,
+            };
+            state = match handled {
+                Some(new_state) => new_state,
+                None => self.emergency_stop(&state).await,
+            };
             let _ = self.state_tx.send(state.clone());
+            self.publish_pose();
 
             // Inner loop to handle long-running tasks (i.e. stepping)
             loop {
@@ -321,12 +1266,18 @@ impl<T: Turntable> TurntableWorker<T> {
                     }
                     _ => {
                         // Continue stepping.
-                        if let TurntableWorkerState::Stepping(stepping_state) = state {
-                            state = match self.capture_step(&stepping_state).await {
-                                Ok(new_state) => new_state,
-                                Err((new_state, _)) => new_state,
+                        if let TurntableWorkerState::Stepping(ref stepping_state) = state {
+                            let stepped = tokio::select! {
+                                result = self.capture_step(stepping_state) => Some(result),
+                                _ = estop.notified() => None,
+                            };
+                            state = match stepped {
+                                Some(Ok(new_state)) => new_state,
+                                Some(Err((new_state, _))) => new_state,
+                                None => self.emergency_stop(&state).await,
                             };
                             let _ = self.state_tx.send(state.clone());
+                            self.publish_pose();
                         }
                     }
                 }
@@ -334,3 +1285,413 @@ impl<T: Turntable> TurntableWorker<T> {
         }
     }
 }
+
+/// Hold on to a command received while a calibration move is timed, to be handled after calibration.
+/// Disconnecting or stopping the table can't wait for the remaining moves, so they end calibration.
+fn defer_during_calibration(
+    deferred: &mut VecDeque<TurntableWorkerCommand>,
+    cmd: TurntableWorkerCommand,
+) -> anyhow::Result<()> {
+    let interrupts = matches!(
+        cmd,
+        TurntableWorkerCommand::Disconnect | TurntableWorkerCommand::StopContinuousRotation
+    );
+    let message = format!("Calibration interrupted by {:?}", cmd);
+    deferred.push_back(cmd);
+    if interrupts {
+        Err(anyhow!(message))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::worker::worker_camera::{CameraId, CaptureError};
+    use tokio::sync::mpsc::unbounded_channel;
+    use tokio::time::Instant;
+
+    fn job(rotation_steps: u16, tilt_steps: u16) -> TurntableSteppingJob {
+        TurntableSteppingJob {
+            rotation: RotationArc {
+                start_deg: 0.0,
+                end_deg: 360.0,
+                spacing: RotationSpacing::Steps(rotation_steps),
+                detail: None,
+            },
+            tilt_lower: -10.0,
+            tilt_upper: 20.0,
+            tilt_steps,
+            ordering: PoseOrdering::Continuous,
+            settle: SettleTimes {
+                first_pose_ms: 0,
+                rotation: AxisTiming {
+                    constant_ms: 0.0,
+                    ms_per_deg: 0.0,
+                },
+                tilt: AxisTiming {
+                    constant_ms: 0.0,
+                    ms_per_deg: 0.0,
+                },
+            },
+            frames: None,
+            cameras: CameraTrigger::Primary,
+            recapture_below: None,
+            motion_check: MotionCheck::Off,
+            background: false,
+            speeds: MotionSpeeds {
+                rotation_deg_per_sec: 10.0,
+                tilt_deg_per_sec: 5.0,
+            },
+            camera_timeouts: CaptureTimeouts::default(),
+        }
+    }
+
+    fn pose(rotation_deg: f32, tilt_deg: f32) -> Pose {
+        Pose {
+            rotation_deg,
+            tilt_deg,
+        }
+    }
+
+    #[test]
+    fn test_job_poses() {
+        let poses = job(4, 3).poses();
+        assert_eq!(poses.len(), 12);
+        assert_eq!(poses[1], pose(90.0, -10.0));
+        // Rotation carries on advancing into the next ring
+        assert_eq!(poses[8], pose(720.0, 20.0));
+    }
+
+    #[test]
+    fn test_background_poses() {
+        let background = TurntableSteppingJob {
+            background: true,
+            ordering: PoseOrdering::TiltMajor,
+            ..job(8, 3)
+        };
+        assert_eq!(
+            background.poses(),
+            [pose(0.0, -10.0), pose(0.0, 5.0), pose(0.0, 20.0)]
+        );
+    }
+
+    #[test]
+    fn test_pose_grid() {
+        let serpentine = TurntableSteppingJob {
+            ordering: PoseOrdering::Serpentine,
+            ..job(3, 2)
+        };
+        let grid = serpentine.grid();
+        assert_eq!(grid.tilts_deg, [-10.0, 20.0]);
+        assert_eq!(grid.rotations_deg, [0.0, 120.0, 240.0]);
+        assert_eq!(grid.cells, [(0, 0), (0, 1), (0, 2), (1, 2), (1, 1), (1, 0)]);
+        for (visited, &(t, r)) in serpentine.poses().iter().zip(&grid.cells) {
+            assert_eq!(*visited, pose(grid.rotations_deg[r], grid.tilts_deg[t]));
+        }
+
+        let background = TurntableSteppingJob {
+            background: true,
+            ..serpentine
+        }
+        .grid();
+        assert_eq!(background.rotations_deg, [0.0]);
+        assert_eq!(background.cells, [(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn test_single_tilt_ring() {
+        assert_eq!(job(8, 1).poses()[3].tilt_deg, -10.0);
+    }
+
+    #[test]
+    fn test_orderings() {
+        let ordered = |ordering| {
+            TurntableSteppingJob {
+                ordering,
+                ..job(3, 2)
+            }
+            .poses()
+        };
+        assert_eq!(
+            ordered(PoseOrdering::Serpentine),
+            [
+                pose(0.0, -10.0),
+                pose(120.0, -10.0),
+                pose(240.0, -10.0),
+                pose(240.0, 20.0),
+                pose(120.0, 20.0),
+                pose(0.0, 20.0),
+            ]
+        );
+        assert_eq!(
+            ordered(PoseOrdering::TiltMajor),
+            [
+                pose(0.0, -10.0),
+                pose(0.0, 20.0),
+                pose(120.0, 20.0),
+                pose(120.0, -10.0),
+                pose(240.0, -10.0),
+                pose(240.0, 20.0),
+            ]
+        );
+        assert_eq!(ordered(PoseOrdering::ReturnHome)[3], pose(0.0, 20.0));
+    }
+
+    #[test]
+    fn test_partial_arc() {
+        let arc = |spacing, detail| RotationArc {
+            start_deg: 0.0,
+            end_deg: 180.0,
+            spacing,
+            detail,
+        };
+        // Both ends of a partial arc are included
+        assert_eq!(
+            arc(RotationSpacing::Steps(5), None).angles(),
+            [0.0, 45.0, 90.0, 135.0, 180.0]
+        );
+        assert_eq!(
+            arc(RotationSpacing::Degrees(70.0), None).angles(),
+            [0.0, 70.0, 140.0]
+        );
+        let detail = DetailSector {
+            start_deg: 80.0,
+            end_deg: 100.0,
+            spacing_deg: 10.0,
+        };
+        assert_eq!(
+            arc(RotationSpacing::Steps(5), Some(detail)).angles(),
+            [0.0, 45.0, 80.0, 90.0, 100.0, 135.0, 180.0]
+        );
+    }
+
+    #[test]
+    fn test_continuous_partial_arc() {
+        let job = TurntableSteppingJob {
+            rotation: RotationArc {
+                start_deg: 0.0,
+                end_deg: 90.0,
+                spacing: RotationSpacing::Steps(3),
+                detail: None,
+            },
+            ..job(3, 2)
+        };
+        // No extra turn between rings, which carry on from where the last one ended
+        assert_eq!(
+            job.poses(),
+            [
+                pose(0.0, -10.0),
+                pose(45.0, -10.0),
+                pose(90.0, -10.0),
+                pose(90.0, 20.0),
+                pose(45.0, 20.0),
+                pose(0.0, 20.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_circle_spacing() {
+        let arc = RotationArc {
+            start_deg: 0.0,
+            end_deg: 360.0,
+            spacing: RotationSpacing::Degrees(90.0),
+            detail: None,
+        };
+        // 360 is the same position as 0
+        assert_eq!(arc.angles(), [0.0, 90.0, 180.0, 270.0]);
+    }
+
+    #[test]
+    fn test_revisits_blurred_steps() {
+        let mut state = TurntableSteppingState {
+            id: 0,
+            job: TurntableSteppingJob {
+                recapture_below: Some(0.5),
+                ..job(4, 1)
+            },
+            step: 0,
+            previous_step: None,
+            rotation_origin_deg: 0.0,
+            timing: JobTiming::new(),
+            pause_reason: None,
+            sharpness: Vec::new(),
+            revisits: None,
+            unmoved: Vec::new(),
+            moved_differences: Vec::new(),
+        };
+        let peers = (CameraId(0), 0);
+        for (step, score) in [(0, 100.0), (1, 20.0), (2, 90.0), (3, 30.0)] {
+            state.sharpness.push((step, peers, score));
+        }
+        assert_eq!(state.next_step(), Some(1));
+        state.step = 3;
+        assert_eq!(state.next_step(), Some(1));
+        assert_eq!(state.progress(), 1.0);
+        assert_eq!(state.next_step(), Some(3));
+        assert_eq!(state.next_step(), None);
+    }
+
+    #[test]
+    fn test_settle_times() {
+        let job = TurntableSteppingJob {
+            ordering: PoseOrdering::Serpentine,
+            settle: SettleTimes {
+                first_pose_ms: 2000,
+                rotation: AxisTiming {
+                    constant_ms: 100.0,
+                    ms_per_deg: 20.0,
+                },
+                tilt: AxisTiming {
+                    constant_ms: 1500.0,
+                    ms_per_deg: 0.0,
+                },
+            },
+            ..job(4, 2)
+        };
+        let settle_ms = |job: &TurntableSteppingJob, from, step| {
+            (job.settle_time(from, step).as_secs_f32() * 1000.0).round()
+        };
+        assert_eq!(settle_ms(&job, None, 0), 2000.0);
+        // 90 degree rotation
+        assert_eq!(settle_ms(&job, Some(0), 1), 1900.0);
+        // Tilt only, between serpentine rings
+        assert_eq!(settle_ms(&job, Some(3), 4), 1500.0);
+        // Revisiting a pose after the last one, with a tilt and a 180 degree rotation back
+        assert_eq!(settle_ms(&job, Some(7), 2), 3700.0);
+        // Rotating and tilting together waits for the longer of the two
+        let continuous = TurntableSteppingJob {
+            ordering: PoseOrdering::Continuous,
+            ..job
+        };
+        assert_eq!(settle_ms(&continuous, Some(3), 4), 1900.0);
+    }
+
+    #[test]
+    fn test_estimated_move_time() {
+        let serpentine = TurntableSteppingJob {
+            ordering: PoseOrdering::Serpentine,
+            ..job(4, 2)
+        };
+        // Tilt to -10, turn 270 and back at 10 deg/s, plus one 30 degree tilt at 5 deg/s
+        assert_eq!(serpentine.estimated_move_time().as_secs_f32().round(), 62.0);
+        assert!(job(4, 2).estimated_move_time() > serpentine.estimated_move_time());
+    }
+
+    #[test]
+    fn test_commands_deferred_during_calibration() {
+        let mut deferred = VecDeque::new();
+        let jog = TurntableWorkerCommand::JogRotation { degrees: 10.0 };
+        assert!(defer_during_calibration(&mut deferred, jog).is_ok());
+        assert!(
+            defer_during_calibration(&mut deferred, TurntableWorkerCommand::Disconnect).is_err()
+        );
+        // Both are kept to handle afterwards, in the order they arrived
+        assert!(matches!(
+            deferred.make_contiguous(),
+            [
+                TurntableWorkerCommand::JogRotation { .. },
+                TurntableWorkerCommand::Disconnect
+            ]
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_emergency_stop_interrupts_move() {
+        let (cmd_tx, cmd_rx) = unbounded_channel();
+        let (state_tx, mut state_rx) = unbounded_channel();
+        let (camera_cmd_tx, _camera_cmd_rx) = unbounded_channel();
+        let (pose_tx, pose_rx) = watch::channel(None);
+        let estop = Arc::new(Notify::new());
+        let worker = TurntableWorker::new(
+            cmd_rx,
+            state_tx,
+            camera_cmd_tx,
+            watch::channel(Vec::new()).1,
+            pose_tx,
+            TrafficLog::default(),
+            estop.clone(),
+        );
+        tokio::spawn(worker.run());
+
+        let settings = DriverSettings::Simulated {
+            rotation_speed: 10.0,
+            tilt_speed: 10.0,
+        };
+        cmd_tx
+            .send(TurntableWorkerCommand::Connect { settings })
+            .unwrap();
+        while !matches!(state_rx.recv().await, Some(TurntableWorkerState::Connected)) {}
+
+        // Stop part way through a 9 second move
+        let start = Instant::now();
+        cmd_tx
+            .send(TurntableWorkerCommand::JogRotation { degrees: 90.0 })
+            .unwrap();
+        sleep(Duration::from_secs(1)).await;
+        estop.notify_one();
+        assert!(matches!(
+            state_rx.recv().await,
+            Some(TurntableWorkerState::Connected)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(*pose_rx.borrow(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_camera_timeout_pauses_job() {
+        let (cmd_tx, cmd_rx) = unbounded_channel();
+        let (state_tx, mut state_rx) = unbounded_channel();
+        let (camera_cmd_tx, mut camera_cmd_rx) = unbounded_channel();
+        let (pose_tx, _pose_rx) = watch::channel(None);
+        let worker = TurntableWorker::new(
+            cmd_rx,
+            state_tx,
+            camera_cmd_tx,
+            watch::channel(Vec::new()).1,
+            pose_tx,
+            TrafficLog::default(),
+            Arc::new(Notify::new()),
+        );
+        tokio::spawn(worker.run());
+
+        let settings = DriverSettings::Simulated {
+            rotation_speed: 10.0,
+            tilt_speed: 10.0,
+        };
+        cmd_tx
+            .send(TurntableWorkerCommand::Connect { settings })
+            .unwrap();
+        while !matches!(state_rx.recv().await, Some(TurntableWorkerState::Connected)) {}
+
+        cmd_tx
+            .send(TurntableWorkerCommand::Step { job: job(4, 1) })
+            .unwrap();
+        let Some(CameraWorkerCommand::CaptureImage {
+            seq: 0,
+            reply: Some(reply),
+            ..
+        }) = camera_cmd_rx.recv().await
+        else {
+            panic!("Expected a capture request for the first pose");
+        };
+        let error = CaptureError::TimedOut {
+            cameras: vec![CameraId(0)],
+            message: "cam0: Camera took longer than 30s to capture".to_string(),
+        };
+        reply.send(Err(error.clone())).unwrap();
+        loop {
+            match state_rx.recv().await {
+                Some(TurntableWorkerState::Paused(state)) => {
+                    assert_eq!(state.overall_step(), 0);
+                    assert_eq!(state.pause_reason(), Some(error.to_string().as_str()));
+                    break;
+                }
+                Some(TurntableWorkerState::Stepping(_)) => {}
+                other => panic!("Unexpected state {:?}", other),
+            }
+        }
+    }
+}
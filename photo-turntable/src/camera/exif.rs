@@ -0,0 +1,239 @@
+//! The exposure settings a camera recorded in an image's EXIF data.
+//!
+//! Only the handful of tags shown when inspecting an image are read. The JPEG decoder hands back the
+//! EXIF block as a TIFF structure: a first IFD holding the camera's clock time and a pointer to the
+//! EXIF IFD, which holds the exposure.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use image::{codecs::jpeg::JpegDecoder, ImageDecoder};
+
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_EXPOSURE_TIME: u16 = 0x829a;
+const TAG_F_NUMBER: u16 = 0x829d;
+const TAG_ISO: u16 = 0x8827;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_FOCAL_LENGTH: u16 = 0x920a;
+
+const FORMAT_ASCII: u16 = 2;
+const FORMAT_SHORT: u16 = 3;
+const FORMAT_LONG: u16 = 4;
+const FORMAT_RATIONAL: u16 = 5;
+
+/// Exposure settings of an image, as far as the camera recorded them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Exif {
+    /// Seconds, as a fraction
+    pub(crate) exposure_time: Option<(u32, u32)>,
+    pub(crate) f_number: Option<f32>,
+    pub(crate) iso: Option<u32>,
+    /// Millimetres
+    pub(crate) focal_length: Option<f32>,
+    /// When the picture was taken, by the camera's clock, as `YYYY:MM:DD HH:MM:SS`
+    pub(crate) timestamp: Option<String>,
+}
+
+impl Exif {
+    /// Exposure time as photographers write it, e.g. `1/125 s` or `2 s`.
+    pub(crate) fn shutter(&self) -> Option<String> {
+        let (numerator, denominator) = self.exposure_time?;
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+        Some(match numerator >= denominator {
+            true => format!("{} s", numerator as f32 / denominator as f32),
+            false => format!("1/{} s", (denominator as f32 / numerator as f32).round()),
+        })
+    }
+}
+
+/// A TIFF structure, read in its own byte order.
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+/// One field of an IFD.
+struct Entry {
+    tag: u16,
+    format: u16,
+    count: u32,
+    /// Offset of the 4 byte value field, holding the value itself if it fits
+    value_field: usize,
+}
+
+impl Tiff<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.data.get(offset..offset + N)?.try_into().ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.bytes(offset)?;
+        Some(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes(offset)?;
+        Some(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+
+    fn entries(&self, ifd_offset: usize) -> Vec<Entry> {
+        let count = self.u16(ifd_offset).unwrap_or(0) as usize;
+        (0..count)
+            .map_while(|i| {
+                let entry = ifd_offset + 2 + i * 12;
+                Some(Entry {
+                    tag: self.u16(entry)?,
+                    format: self.u16(entry + 2)?,
+                    count: self.u32(entry + 4)?,
+                    value_field: entry + 8,
+                })
+            })
+            .collect()
+    }
+
+    /// Where an entry's value is, which is elsewhere in the data if it doesn't fit the value field.
+    fn value_offset(&self, entry: &Entry, size: usize) -> Option<usize> {
+        match size * entry.count as usize <= 4 {
+            true => Some(entry.value_field),
+            false => Some(self.u32(entry.value_field)? as usize),
+        }
+    }
+
+    fn integer(&self, entry: &Entry) -> Option<u32> {
+        match entry.format {
+            FORMAT_SHORT => self.u16(entry.value_field).map(u32::from),
+            FORMAT_LONG => self.u32(entry.value_field),
+            _ => None,
+        }
+    }
+
+    fn rational(&self, entry: &Entry) -> Option<(u32, u32)> {
+        if entry.format != FORMAT_RATIONAL {
+            return None;
+        }
+        let offset = self.value_offset(entry, 8)?;
+        Some((self.u32(offset)?, self.u32(offset + 4)?))
+    }
+
+    fn ascii(&self, entry: &Entry) -> Option<String> {
+        if entry.format != FORMAT_ASCII {
+            return None;
+        }
+        let offset = self.value_offset(entry, 1)?;
+        let bytes = self.data.get(offset..offset + entry.count as usize)?;
+        let text = String::from_utf8_lossy(bytes);
+        Some(text.trim_end_matches('\0').trim().to_string()).filter(|text| !text.is_empty())
+    }
+}
+
+/// Read the exposure settings from an EXIF block, starting at its TIFF header.
+fn parse_exif(data: &[u8]) -> Option<Exif> {
+    let big_endian = match data.get(..4)? {
+        [0x49, 0x49, 42, 0] => false,
+        [0x4d, 0x4d, 0, 42] => true,
+        _ => return None,
+    };
+    let tiff = Tiff { data, big_endian };
+    let mut exif = Exif::default();
+    let mut exif_ifd = None;
+    for entry in tiff.entries(tiff.u32(4)? as usize) {
+        match entry.tag {
+            TAG_DATE_TIME => exif.timestamp = tiff.ascii(&entry),
+            TAG_EXIF_IFD => exif_ifd = tiff.integer(&entry),
+            _ => {}
+        }
+    }
+    let ratio = |(numerator, denominator): (u32, u32)| numerator as f32 / denominator as f32;
+    for entry in exif_ifd.map_or(Vec::new(), |offset| tiff.entries(offset as usize)) {
+        match entry.tag {
+            TAG_EXPOSURE_TIME => exif.exposure_time = tiff.rational(&entry),
+            TAG_F_NUMBER => exif.f_number = tiff.rational(&entry).map(ratio),
+            TAG_ISO => exif.iso = tiff.integer(&entry),
+            TAG_FOCAL_LENGTH => exif.focal_length = tiff.rational(&entry).map(ratio),
+            // When the shutter fired, rather than when the file was last changed
+            TAG_DATE_TIME_ORIGINAL => {
+                exif.timestamp = tiff.ascii(&entry).or(exif.timestamp.take());
+            }
+            _ => {}
+        }
+    }
+    Some(exif)
+}
+
+/// Read the exposure settings recorded in a JPEG.
+pub(crate) fn read_exif(path: &Path) -> anyhow::Result<Exif> {
+    let mut decoder = JpegDecoder::new(BufReader::new(File::open(path)?))?;
+    let data = decoder
+        .exif_metadata()?
+        .ok_or(anyhow::anyhow!("Image has no EXIF data"))?;
+    parse_exif(&data).ok_or(anyhow::anyhow!("EXIF data isn't a TIFF structure"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// EXIF block with a first IFD pointing to an EXIF IFD holding the exposure.
+    fn exif_block(big_endian: bool) -> Vec<u8> {
+        let u16_bytes = |value: u16| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let u32_bytes = |value: u32| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let mut data = match big_endian {
+            true => vec![0x4d, 0x4d, 0, 42],
+            false => vec![0x49, 0x49, 42, 0],
+        };
+        data.extend(u32_bytes(8));
+        let entry = |data: &mut Vec<u8>, tag, format, count, value: u32| {
+            data.extend(u16_bytes(tag));
+            data.extend(u16_bytes(format));
+            data.extend(u32_bytes(count));
+            match format {
+                FORMAT_SHORT => data.extend(u16_bytes(value as u16).into_iter().chain([0, 0])),
+                _ => data.extend(u32_bytes(value)),
+            }
+        };
+        // First IFD at 8, EXIF IFD at 26, values from 92
+        data.extend(u16_bytes(1));
+        entry(&mut data, TAG_EXIF_IFD, FORMAT_LONG, 1, 26);
+        data.extend(u32_bytes(0));
+        data.extend(u16_bytes(5));
+        entry(&mut data, TAG_EXPOSURE_TIME, FORMAT_RATIONAL, 1, 92);
+        entry(&mut data, TAG_F_NUMBER, FORMAT_RATIONAL, 1, 100);
+        entry(&mut data, TAG_ISO, FORMAT_SHORT, 1, 200);
+        entry(&mut data, TAG_FOCAL_LENGTH, FORMAT_RATIONAL, 1, 108);
+        entry(&mut data, TAG_DATE_TIME_ORIGINAL, FORMAT_ASCII, 20, 116);
+        data.extend(u32_bytes(0));
+        assert_eq!(data.len(), 92);
+        for value in [1, 125, 56, 10, 100, 1] {
+            data.extend(u32_bytes(value));
+        }
+        data.extend(b"2025:06:01 14:30:05\0");
+        data
+    }
+
+    #[test]
+    fn test_parse_exif() {
+        for big_endian in [false, true] {
+            let exif = parse_exif(&exif_block(big_endian)).unwrap();
+            assert_eq!(exif.shutter().as_deref(), Some("1/125 s"));
+            assert_eq!(exif.f_number, Some(5.6));
+            assert_eq!(exif.iso, Some(200));
+            assert_eq!(exif.focal_length, Some(100.0));
+            assert_eq!(exif.timestamp.as_deref(), Some("2025:06:01 14:30:05"));
+        }
+        assert_eq!(parse_exif(b"not exif"), None);
+    }
+}
@@ -1,7 +1,107 @@
-use std::path::Path;
+use std::{
+    fmt,
+    future::Future,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use anyhow::Error;
-use gphoto2::{file::CameraFile, list::CameraListIter, Context};
+use anyhow::{anyhow, Error};
+use gphoto2::{
+    file::{CameraFile, CameraFilePath},
+    list::CameraListIter,
+    task::Task,
+    widget::{RadioWidget, Widget},
+    Context,
+};
+use serde::Serialize;
+
+mod exif;
+
+pub(crate) use exif::{read_exif, Exif};
+
+/// gphoto2 config holding the exposure compensation, in stops.
+const EXPOSURE_COMPENSATION_CONFIG: &str = "exposurecompensation";
+
+/// gphoto2 config that moves the lens focus when set. Canon bodies offer `Near 1`-`Far 3` choices,
+/// Nikon bodies a signed range.
+const MANUAL_FOCUS_DRIVE_CONFIG: &str = "manualfocusdrive";
+
+/// Largest Canon focus drive step, `Near 3` or `Far 3`.
+const MAX_FOCUS_DRIVE_CHOICE: i32 = 3;
+
+/// Frames captured at each pose in place of a single image, grouped under that pose.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FrameSet {
+    Bracket(ExposureBracket),
+    FocusStack(FocusStack),
+}
+
+impl FrameSet {
+    /// Setting for each frame, in capture order.
+    pub(crate) fn settings(&self) -> Vec<FrameSetting> {
+        match self {
+            FrameSet::Bracket(bracket) => bracket
+                .stops
+                .iter()
+                .map(|&stops| FrameSetting::ExposureStops(stops))
+                .collect(),
+            FrameSet::FocusStack(stack) => (0..stack.steps).map(FrameSetting::FocusStep).collect(),
+        }
+    }
+}
+
+/// What distinguishes one frame of a [`FrameSet`] from the others.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FrameSetting {
+    /// Exposure compensation, in stops
+    ExposureStops(f32),
+    /// Number of focus drives from the starting focus
+    FocusStep(u16),
+}
+
+/// Frames focused progressively further away, to merge into one image with everything in focus.
+/// The lens should start focused on the nearest part of the object. It's driven back afterwards,
+/// though focus-by-wire lenses may not return exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FocusStack {
+    pub(crate) steps: u16,
+    /// Focus drive between frames, positive moving further away. For Canon bodies, 1 to 3 selects
+    /// the drive step size. For Nikon bodies, it's in the camera's own units
+    pub(crate) drive: i32,
+}
+
+/// How the frames of an exposure bracket are exposed differently.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BracketMethod {
+    /// Change the camera's exposure compensation before each frame
+    ConfigChange,
+    /// Press the shutter once per frame, with the camera's own auto exposure bracketing set up on the body
+    CameraAeb,
+}
+
+/// Several differently exposed frames, captured in place of a single image.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExposureBracket {
+    /// Exposure compensation of each frame, in stops. For camera AEB, only the count is used
+    pub(crate) stops: Vec<f32>,
+    pub(crate) method: BracketMethod,
+}
+
+impl ExposureBracket {
+    /// `frames` exposures centred on zero, `step` stops apart.
+    pub(crate) fn centred(frames: u8, step: f32, method: BracketMethod) -> Self {
+        let middle = (frames as f32 - 1.0) / 2.0;
+        Self {
+            stops: (0..frames).map(|i| (i as f32 - middle) * step).collect(),
+            method,
+        }
+    }
+}
 
 pub(crate) struct CameraContext {
     pub(super) context: gphoto2::Context,
@@ -28,9 +128,15 @@ pub(crate) struct CameraSpec {
 }
 
 impl CameraSpec {
-    pub(crate) fn connect(&self, context: &CameraContext) -> Result<Camera, Error> {
-        let device = context.context.get_camera(&self.descriptor).wait()?;
-        Ok(Camera { device })
+    pub(crate) async fn connect(
+        &self,
+        context: &CameraContext,
+        limit: Duration,
+    ) -> Result<Camera, Error> {
+        let stuck = Arc::new(AtomicBool::new(false));
+        let task = context.context.get_camera(&self.descriptor);
+        let device = within(task, &stuck, "connect", limit).await?;
+        Ok(Camera { device, stuck })
     }
 
     pub(crate) fn name(&self) -> String {
@@ -42,19 +148,259 @@ impl CameraSpec {
 #[derive(Clone)]
 pub(crate) struct Camera {
     device: gphoto2::Camera,
+    /// Set while an operation that timed out is still running in libgphoto2
+    stuck: Arc<AtomicBool>,
+}
+
+/// A camera operation that didn't finish in time, and was cancelled.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CameraTimeout {
+    pub(crate) operation: &'static str,
+    pub(crate) limit: Duration,
+}
+
+impl fmt::Display for CameraTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Camera took longer than {:?} to {}",
+            self.limit, self.operation
+        )
+    }
+}
+
+impl std::error::Error for CameraTimeout {}
+
+/// A libgphoto2 call that can be given up on.
+trait CameraTask: Future + Unpin + Send + 'static {
+    /// Ask libgphoto2 to stop the call. It only checks between steps, so the call may run on.
+    fn cancel(&self);
+    /// Block until the call returns, however long that takes.
+    fn finish(self);
+}
+
+impl<T: 'static + Send> CameraTask for Task<T> {
+    fn cancel(&self) {
+        Task::cancel(self);
+    }
+
+    fn finish(self) {
+        self.wait();
+    }
+}
+
+/// Wait for a gphoto2 task, asking libgphoto2 to cancel it if it runs past `limit`.
+///
+/// Cancelling only sets a flag, and gphoto2 runs every call on one thread, so a hung call holds up
+/// every later one. `stuck` stays set until the abandoned call actually returns.
+async fn within<F, T>(
+    mut task: F,
+    stuck: &Arc<AtomicBool>,
+    operation: &'static str,
+    limit: Duration,
+) -> Result<T, Error>
+where
+    F: CameraTask<Output = gphoto2::Result<T>>,
+{
+    match tokio::time::timeout(limit, &mut task).await {
+        Ok(result) => Ok(result?),
+        Err(_) => {
+            task.cancel();
+            stuck.store(true, Ordering::Relaxed);
+            let stuck = stuck.clone();
+            tokio::task::spawn_blocking(move || {
+                task.finish();
+                stuck.store(false, Ordering::Relaxed);
+            });
+            Err(CameraTimeout { operation, limit }.into())
+        }
+    }
 }
 
 impl Camera {
-    pub(crate) async fn capture(&self, path: &Path) -> Result<CameraFile, Error> {
+    /// Whether an operation that timed out is still running. Until it returns, gphoto2 can't run
+    /// anything else, so the camera can't be used or reconnected.
+    pub(crate) fn is_stuck(&self) -> bool {
+        self.stuck.load(Ordering::Relaxed)
+    }
+
+    /// Take a picture, returning where the camera stored it.
+    pub(crate) async fn capture(&self, limit: Duration) -> Result<CameraFilePath, Error> {
+        within(self.device.capture_image(), &self.stuck, "capture", limit).await
+    }
+
+    /// Set exposure compensation to the available setting nearest `stops`, returning the previous setting.
+    pub(crate) async fn set_exposure_compensation(&self, stops: f32) -> Result<String, Error> {
+        let widget = self
+            .device
+            .config_key::<RadioWidget>(EXPOSURE_COMPENSATION_CONFIG)
+            .await?;
+        let previous = widget.choice();
+        let nearest = widget
+            .choices_iter()
+            .filter_map(|choice| Some((parse_stops(&choice)?, choice)))
+            .min_by(|(a, _), (b, _)| (a - stops).abs().total_cmp(&(b - stops).abs()))
+            .ok_or(anyhow!(
+                "Camera has no usable exposure compensation settings"
+            ))?;
+        widget.set_choice(&nearest.1)?;
+        self.device.set_config(&widget).await?;
+        Ok(previous)
+    }
+
+    /// Put back an exposure compensation setting returned by [`Camera::set_exposure_compensation`].
+    pub(crate) async fn restore_exposure_compensation(&self, choice: &str) -> Result<(), Error> {
+        let widget = self
+            .device
+            .config_key::<RadioWidget>(EXPOSURE_COMPENSATION_CONFIG)
+            .await?;
+        widget.set_choice(choice)?;
+        self.device.set_config(&widget).await?;
+        Ok(())
+    }
+
+    /// Move the lens focus by `drive`, further away if positive. See [`FocusStack::drive`] for units.
+    pub(crate) async fn drive_focus(&self, drive: i32) -> Result<(), Error> {
+        let widget = self
+            .device
+            .config_key::<Widget>(MANUAL_FOCUS_DRIVE_CONFIG)
+            .await?;
+        match &widget {
+            Widget::Radio(radio) => {
+                let direction = if drive < 0 { "Near" } else { "Far" };
+                let size = drive.abs().clamp(1, MAX_FOCUS_DRIVE_CHOICE);
+                radio.set_choice(&format!("{} {}", direction, size))?;
+            }
+            Widget::Range(range) => range.set_value(drive as f32),
+            _ => return Err(anyhow!("Camera doesn't support driving focus")),
+        }
+        self.device.set_config(&widget).await?;
+        Ok(())
+    }
+
+    /// Download a captured picture to `path`.
+    pub(crate) async fn download(
+        &self,
+        file_path: &CameraFilePath,
+        path: &Path,
+        limit: Duration,
+    ) -> Result<CameraFile, Error> {
         let camera_fs = self.device.fs();
+        let task = camera_fs.download_to(&file_path.folder(), &file_path.name(), path);
+        within(task, &self.stuck, "download", limit).await
+    }
+}
 
-        // And take pictures
-        let file_path = self.device.capture_image().await?;
+/// Parse an exposure compensation choice, such as `-0.7`, `+1 1/3` or `2/3`, into stops.
+fn parse_stops(choice: &str) -> Option<f32> {
+    let choice = choice.trim();
+    let (sign, magnitude) = match choice.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, choice.strip_prefix('+').unwrap_or(choice)),
+    };
+    if magnitude.trim().is_empty() {
+        return None;
+    }
+    let mut stops = 0.0;
+    for part in magnitude.split_whitespace() {
+        stops += match part.split_once('/') {
+            Some((numerator, denominator)) => {
+                numerator.parse::<f32>().ok()? / denominator.parse::<f32>().ok()?
+            }
+            None => part.parse::<f32>().ok()?,
+        };
+    }
+    Some(sign * stops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stops() {
+        assert_eq!(parse_stops("0"), Some(0.0));
+        assert_eq!(parse_stops("-0.7"), Some(-0.7));
+        assert_eq!(parse_stops("+1 1/2"), Some(1.5));
+        assert_eq!(parse_stops("-2/3"), Some(-2.0 / 3.0));
+        assert_eq!(parse_stops("Auto"), None);
+        assert_eq!(parse_stops(""), None);
+    }
 
-        let file = camera_fs
-            .download_to(&file_path.folder(), &file_path.name(), path)
-            .wait()?;
+    /// Never returns until `release` is sent, like a camera that has hung.
+    struct HungTask {
+        cancelled: Arc<AtomicBool>,
+        release: std::sync::mpsc::Receiver<()>,
+    }
+
+    impl Future for HungTask {
+        type Output = gphoto2::Result<()>;
+
+        fn poll(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Self::Output> {
+            std::task::Poll::Pending
+        }
+    }
+
+    impl CameraTask for HungTask {
+        fn cancel(&self) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+
+        fn finish(self) {
+            let _ = self.release.recv();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_within_gives_up_on_hung_task() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (release_tx, release) = std::sync::mpsc::channel();
+        let task = HungTask {
+            cancelled: cancelled.clone(),
+            release,
+        };
+        let stuck = Arc::new(AtomicBool::new(false));
+        let limit = Duration::from_millis(20);
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            within(task, &stuck, "capture", limit),
+        )
+        .await
+        .expect("within should return once the limit passes");
+        let e = result.unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<CameraTimeout>(),
+            Some(&CameraTimeout {
+                operation: "capture",
+                limit
+            })
+        );
+        assert!(cancelled.load(Ordering::Relaxed));
+        assert!(stuck.load(Ordering::Relaxed));
+
+        // The runtime is free to handle other work while the call hangs
+        let (ping_tx, ping_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move { ping_tx.send(()).unwrap() });
+        ping_rx.await.unwrap();
+        assert!(stuck.load(Ordering::Relaxed));
+
+        release_tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while stuck.load(Ordering::Relaxed) {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("stuck should clear once the call returns");
+    }
 
-        Ok(file)
+    #[test]
+    fn test_centred_bracket() {
+        let bracket = ExposureBracket::centred(3, 1.5, BracketMethod::ConfigChange);
+        assert_eq!(bracket.stops, [-1.5, 0.0, 1.5]);
     }
 }
@@ -3,6 +3,9 @@ use eframe::NativeOptions;
 
 mod app;
 mod camera;
+mod mask;
+mod merge;
+mod quality;
 mod turntable;
 
 fn main() -> Result<(), eframe::Error> {
@@ -10,6 +13,6 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Turntable Controller",
         native_options,
-        Box::new(|cc| Ok(Box::new(TurntableApp::<turntable::RevoTurntable>::new(cc)))),
+        Box::new(|cc| Ok(Box::new(TurntableApp::new(cc)))),
     )
 }
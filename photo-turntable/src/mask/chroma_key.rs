@@ -0,0 +1,117 @@
+//! Keying out a coloured backdrop, for setups shot against a green or blue screen.
+//!
+//! Colours are compared by chroma alone, so shading across the backdrop doesn't matter. Spill is the
+//! backdrop's colour cast on the object; it's removed by taking out the part of each pixel's chroma
+//! that points towards the key colour, keeping the pixel's luma.
+
+use image::{GrayImage, Luma, RgbImage, Rgba, RgbaImage};
+
+use super::morphology;
+
+/// Chroma distance over which alpha ramps up beyond the tolerance, so noise near the threshold
+/// doesn't leave a ragged edge.
+const EDGE_SOFTNESS: f32 = 0.05;
+
+/// BT.601 weights of red and blue in luma, with green making up the rest.
+const LUMA_RED: f32 = 0.299;
+const LUMA_BLUE: f32 = 0.114;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ChromaKey {
+    /// Colour of the backdrop
+    pub(crate) color: [u8; 3],
+    /// Chroma distance from the key colour within which pixels are backdrop.
+    /// Saturated primaries are around 0.5 to 0.7 apart
+    pub(crate) tolerance: f32,
+    /// How much of the key colour's cast to take out of the object, from 0 to 1
+    pub(crate) spill: f32,
+    /// Radius in pixels of the blur softening the alpha edge, or 0 for none
+    pub(crate) feather_radius: u32,
+}
+
+impl Default for ChromaKey {
+    fn default() -> Self {
+        Self {
+            color: [0, 177, 64],
+            tolerance: 0.15,
+            spill: 0.5,
+            feather_radius: 2,
+        }
+    }
+}
+
+/// Luma and the two chroma components of a pixel, all in 0..1 for luma and about -0.5..0.5 for chroma.
+fn to_ycc([r, g, b]: [u8; 3]) -> (f32, [f32; 2]) {
+    let [r, g, b] = [r, g, b].map(|c| c as f32 / 255.0);
+    let y = LUMA_RED * r + (1.0 - LUMA_RED - LUMA_BLUE) * g + LUMA_BLUE * b;
+    (y, [(b - y) * 0.564, (r - y) * 0.713])
+}
+
+fn from_ycc(y: f32, [cb, cr]: [f32; 2]) -> [u8; 3] {
+    let r = y + cr / 0.713;
+    let b = y + cb / 0.564;
+    let g = (y - LUMA_RED * r - LUMA_BLUE * b) / (1.0 - LUMA_RED - LUMA_BLUE);
+    [r, g, b].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+impl ChromaKey {
+    /// The image with the backdrop made transparent and spill taken out of what's left.
+    pub(crate) fn cut_out(&self, image: &RgbImage) -> RgbaImage {
+        let (_, key) = to_ycc(self.color);
+        let key_length = key[0].hypot(key[1]).max(f32::EPSILON);
+        let direction = key.map(|c| c / key_length);
+
+        let mut cutout = RgbaImage::new(image.width(), image.height());
+        let mut alpha = GrayImage::new(image.width(), image.height());
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let (luma, mut chroma) = to_ycc(pixel.0);
+            let distance = (chroma[0] - key[0]).hypot(chroma[1] - key[1]);
+            let opacity = ((distance - self.tolerance) / EDGE_SOFTNESS).clamp(0.0, 1.0);
+            alpha.put_pixel(x, y, Luma([(opacity * 255.0).round() as u8]));
+
+            let towards_key = chroma[0] * direction[0] + chroma[1] * direction[1];
+            if towards_key > 0.0 {
+                for (c, d) in chroma.iter_mut().zip(direction) {
+                    *c -= self.spill * towards_key * d;
+                }
+            }
+            let [r, g, b] = from_ycc(luma, chroma);
+            cutout.put_pixel(x, y, Rgba([r, g, b, 0]));
+        }
+
+        let alpha = morphology::feather(&alpha, self.feather_radius);
+        for (pixel, opacity) in cutout.pixels_mut().zip(alpha.pixels()) {
+            pixel[3] = opacity[0];
+        }
+        cutout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_cut_out() {
+        let key = ChromaKey {
+            feather_radius: 0,
+            ..ChromaKey::default()
+        };
+        // A shaded green backdrop, with a grey object tinted green by spill
+        let image = RgbImage::from_fn(10, 1, |x, _| match x {
+            0..=4 => Rgb([0, 120 + 20 * x as u8, 45]),
+            _ => Rgb([120, 150, 120]),
+        });
+        let cutout = key.cut_out(&image);
+        assert!(cutout.pixels().take(5).all(|pixel| pixel[3] == 0));
+
+        let object = cutout.get_pixel(7, 0);
+        assert_eq!(object[3], 255);
+        let [r, g, b] = [object[0], object[1], object[2]].map(|c| c as i16);
+        assert!(g - r < 25 && g - b < 25, "spill left in {:?}", object);
+
+        let untouched = ChromaKey { spill: 0.0, ..key }.cut_out(&image);
+        assert_eq!(untouched.get_pixel(7, 0).0, [120, 150, 120, 255]);
+    }
+}
@@ -0,0 +1,187 @@
+//! Object masks, made either by comparing each capture with a plate of the empty table at the same
+//! tilt, or by keying out a coloured backdrop.
+//!
+//! With a plate, pixels differing from it by more than a threshold are taken to be the object.
+//! Opening the result then removes specks of sensor noise, and closing it fills small holes where
+//! the object happens to match the background.
+
+mod chroma_key;
+mod morphology;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use image::{DynamicImage, GrayImage, Luma, RgbImage, RgbaImage};
+
+pub(crate) use chroma_key::ChromaKey;
+
+/// How masks are made from the difference between a capture and its background plate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MaskSettings {
+    /// Smallest difference in any channel, as a fraction of full scale, counted as the object
+    pub(crate) threshold: f32,
+    /// Radius in pixels of the opening and closing that clean up the mask, or 0 for none
+    pub(crate) cleanup_radius: u32,
+}
+
+impl Default for MaskSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            cleanup_radius: 4,
+        }
+    }
+}
+
+/// Mask of `image` against `plate`, white where the object is and black elsewhere.
+pub(crate) fn object_mask(
+    image: &RgbImage,
+    plate: &RgbImage,
+    settings: &MaskSettings,
+) -> GrayImage {
+    let threshold = settings.threshold * 255.0;
+    let mask = GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let difference = image
+            .get_pixel(x, y)
+            .0
+            .iter()
+            .zip(plate.get_pixel(x, y).0)
+            .map(|(a, b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0);
+        match difference as f32 > threshold {
+            true => Luma([255]),
+            false => Luma([0]),
+        }
+    });
+    let radius = settings.cleanup_radius;
+    morphology::close(&morphology::open(&mask, radius), radius)
+}
+
+/// How the object is told apart from its surroundings.
+#[derive(Debug, Clone)]
+pub(crate) enum Keying {
+    /// By its difference from a plate of the empty table
+    Plate {
+        plate: PathBuf,
+        settings: MaskSettings,
+    },
+    /// By keying out the colour of the backdrop
+    Chroma(ChromaKey),
+}
+
+/// What's written for each masked image.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum MaskOutput {
+    /// Greyscale, white where the object is
+    #[default]
+    Mask,
+    /// The image itself, transparent around the object
+    Cutout,
+}
+
+impl MaskOutput {
+    pub(crate) const ALL: [MaskOutput; 2] = [MaskOutput::Mask, MaskOutput::Cutout];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            MaskOutput::Mask => "Masks",
+            MaskOutput::Cutout => "RGBA cutouts",
+        }
+    }
+}
+
+/// Load `image`, separate the object from its surroundings and save the mask or cutout to `output`
+/// as a PNG. A cutout takes its colour from `colour` if given, e.g. a merge of `image` with other
+/// frames, while the mask still comes from `image`.
+pub(crate) fn mask_file(
+    image: &Path,
+    colour: Option<&Path>,
+    keying: &Keying,
+    mask_output: MaskOutput,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let image = image::open(image)?.to_rgb8();
+    let mut cutout = match keying {
+        Keying::Plate { plate, settings } => {
+            let plate = image::open(plate)?.to_rgb8();
+            if image.dimensions() != plate.dimensions() {
+                return Err(anyhow!("Background plate differs in size from the image"));
+            }
+            let mask = object_mask(&image, &plate, settings);
+            RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+                let [r, g, b] = image.get_pixel(x, y).0;
+                image::Rgba([r, g, b, mask.get_pixel(x, y)[0]])
+            })
+        }
+        Keying::Chroma(key) => key.cut_out(&image),
+    };
+    if let (MaskOutput::Cutout, Some(colour)) = (mask_output, colour) {
+        let colour = image::open(colour)?.to_rgb8();
+        if colour.dimensions() != cutout.dimensions() {
+            return Err(anyhow!("Merged image differs in size from its frames"));
+        }
+        cutout = recolour(&cutout, &colour, keying);
+    }
+    match mask_output {
+        MaskOutput::Mask => GrayImage::from_fn(cutout.width(), cutout.height(), |x, y| {
+            Luma([cutout.get_pixel(x, y)[3]])
+        })
+        .save_with_format(output, image::ImageFormat::Png)?,
+        MaskOutput::Cutout => cutout.save_with_format(output, image::ImageFormat::Png)?,
+    }
+    Ok(())
+}
+
+/// `cutout` with the colour of `colour` in place of its own, keeping its transparency.
+fn recolour(cutout: &RgbaImage, colour: &RgbImage, keying: &Keying) -> RgbaImage {
+    let mut recoloured = match keying {
+        Keying::Plate { .. } => DynamicImage::ImageRgb8(colour.clone()).into_rgba8(),
+        // Keying again takes the backdrop's spill out of the new colour too
+        Keying::Chroma(key) => key.cut_out(colour),
+    };
+    for (pixel, masked) in recoloured.pixels_mut().zip(cutout.pixels()) {
+        pixel[3] = masked[3];
+    }
+    recoloured
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_object_mask() {
+        let plate = RgbImage::from_fn(60, 40, |x, y| Rgb([90, 100 + ((x + y) % 3) as u8, 110]));
+        let mut image = plate.clone();
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if (20..40).contains(&x) && (10..30).contains(&y) {
+                *pixel = Rgb([200, 60, 40]);
+            }
+        }
+        // A noisy pixel, and one in the object that matches the background
+        image.put_pixel(5, 5, Rgb([160, 100, 110]));
+        image.put_pixel(30, 20, *plate.get_pixel(30, 20));
+
+        let mask = object_mask(&image, &plate, &MaskSettings::default());
+        assert_eq!(mask.get_pixel(5, 5)[0], 0);
+        assert_eq!(mask.get_pixel(30, 20)[0], 255);
+        assert_eq!(mask.get_pixel(21, 11)[0], 255);
+        assert_eq!(mask.get_pixel(45, 20)[0], 0);
+    }
+
+    #[test]
+    fn test_recolour_keeps_transparency() {
+        let cutout = RgbaImage::from_fn(4, 1, |x, _| image::Rgba([10, 10, 10, (x * 80) as u8]));
+        let merged = RgbImage::from_pixel(4, 1, Rgb([200, 150, 100]));
+        let keying = Keying::Plate {
+            plate: PathBuf::new(),
+            settings: MaskSettings::default(),
+        };
+        let recoloured = recolour(&cutout, &merged, &keying);
+        for (x, _, pixel) in recoloured.enumerate_pixels() {
+            assert_eq!(pixel.0, [200, 150, 100, (x * 80) as u8]);
+        }
+    }
+}
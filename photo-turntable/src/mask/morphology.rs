@@ -0,0 +1,97 @@
+//! Filters over a square window of a single-channel mask: binary erosion and dilation, and a box blur
+//! for feathering soft edges.
+//!
+//! Each is done as a pass along rows then one along columns, summing pixels in a sliding window, so
+//! the cost doesn't grow with the radius. Windows are cut short at the image edges, so objects
+//! touching an edge aren't eaten away from it.
+
+use image::GrayImage;
+
+/// One pass along the rows or columns of `mask`, setting each pixel to `value(sum, count)` for the
+/// `sum` of the `count` pixels within `radius`.
+fn pass(mask: &GrayImage, radius: u32, along_rows: bool, value: fn(u32, u32) -> u8) -> GrayImage {
+    let (width, height) = mask.dimensions();
+    let (lines, length) = match along_rows {
+        true => (height, width),
+        false => (width, height),
+    };
+    let index = |line: u32, i: u32| match along_rows {
+        true => (line * width + i) as usize,
+        false => (i * width + line) as usize,
+    };
+    let source = mask.as_raw();
+    let mut output = GrayImage::new(width, height);
+    let mut prefix = vec![0; length as usize + 1];
+    for line in 0..lines {
+        for i in 0..length {
+            prefix[i as usize + 1] = prefix[i as usize] + source[index(line, i)] as u32;
+        }
+        for i in 0..length {
+            let start = i.saturating_sub(radius) as usize;
+            let end = (i + radius + 1).min(length) as usize;
+            output.as_mut()[index(line, i)] =
+                value(prefix[end] - prefix[start], (end - start) as u32);
+        }
+    }
+    output
+}
+
+/// Both passes of a separable filter.
+fn filter(mask: &GrayImage, radius: u32, value: fn(u32, u32) -> u8) -> GrayImage {
+    pass(&pass(mask, radius, true, value), radius, false, value)
+}
+
+fn dilate(mask: &GrayImage, radius: u32) -> GrayImage {
+    filter(mask, radius, |sum, _| if sum > 0 { 255 } else { 0 })
+}
+
+fn erode(mask: &GrayImage, radius: u32) -> GrayImage {
+    filter(
+        mask,
+        radius,
+        |sum, count| if sum == 255 * count { 255 } else { 0 },
+    )
+}
+
+/// Soften edges by averaging over the window.
+pub(super) fn feather(mask: &GrayImage, radius: u32) -> GrayImage {
+    filter(mask, radius, |sum, count| ((sum + count / 2) / count) as u8)
+}
+
+/// Remove foreground specks smaller than the structuring element.
+pub(super) fn open(mask: &GrayImage, radius: u32) -> GrayImage {
+    dilate(&erode(mask, radius), radius)
+}
+
+/// Fill background holes smaller than the structuring element.
+pub(super) fn close(mask: &GrayImage, radius: u32) -> GrayImage {
+    erode(&dilate(mask, radius), radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn test_open_and_close() {
+        let mut mask = GrayImage::from_fn(30, 30, |x, y| match x < 20 && y >= 10 {
+            true => Luma([255]),
+            false => Luma([0]),
+        });
+        mask.put_pixel(25, 3, Luma([255]));
+        mask.put_pixel(10, 20, Luma([0]));
+
+        let opened = open(&mask, 2);
+        assert_eq!(opened.get_pixel(25, 3)[0], 0);
+        assert_eq!(opened.get_pixel(10, 20)[0], 0);
+        // Corners of larger shapes survive, including where they meet the image edge
+        assert_eq!(opened.get_pixel(19, 10)[0], 255);
+        assert_eq!(opened.get_pixel(0, 29)[0], 255);
+
+        let closed = close(&opened, 2);
+        assert_eq!(closed.get_pixel(10, 20)[0], 255);
+        assert_eq!(closed.get_pixel(25, 3)[0], 0);
+        assert_eq!(closed.get_pixel(20, 10)[0], 0);
+    }
+}
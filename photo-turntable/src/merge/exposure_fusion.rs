@@ -0,0 +1,63 @@
+//! Exposure fusion, after Mertens, Kautz and Van Reeth (2007).
+//!
+//! Each pixel is weighted by how much detail, colour and mid-tone exposure it has in each frame,
+//! producing a well exposed image directly rather than going through an HDR radiance map.
+
+use image::{Luma, Rgb32FImage};
+
+use super::pyramid::{self, WeightMap};
+
+/// Spread of the well-exposedness curve around mid grey.
+const EXPOSURE_SIGMA: f32 = 0.2;
+
+/// Keeps weights from all being zero, e.g. in flat, fully clipped areas.
+const MIN_WEIGHT: f32 = 1e-6;
+
+/// Fuse differently exposed frames of the same scene into one image.
+pub(crate) fn fuse_exposures(frames: &[Rgb32FImage]) -> Rgb32FImage {
+    let mut weights: Vec<_> = frames.iter().map(quality).collect();
+    pyramid::normalize(&mut weights);
+    pyramid::blend(frames, &weights)
+}
+
+/// Product of contrast, saturation and well-exposedness at each pixel.
+fn quality(image: &Rgb32FImage) -> WeightMap {
+    let contrast = pyramid::local_contrast(&pyramid::luminance(image));
+    WeightMap::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        let mean = (r + g + b) / 3.0;
+        let saturation =
+            (((r - mean).powi(2) + (g - mean).powi(2) + (b - mean).powi(2)) / 3.0).sqrt();
+        let exposedness: f32 = [r, g, b]
+            .iter()
+            .map(|v| (-(v - 0.5).powi(2) / (2.0 * EXPOSURE_SIGMA.powi(2))).exp())
+            .product();
+        Luma([contrast.get_pixel(x, y).0[0] * saturation * exposedness + MIN_WEIGHT])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_prefers_well_exposed_frame() {
+        // A textured, coloured scene, captured too dark, well exposed and too bright
+        let scene = |scale: f32| {
+            Rgb32FImage::from_fn(32, 32, |x, y| {
+                let texture = ((x + y) % 2) as f32 * 0.1;
+                Rgb([0.5 + texture, 0.4, 0.3].map(|v| (v * scale).min(1.0)))
+            })
+        };
+        let fused = fuse_exposures(&[scene(0.2), scene(1.0), scene(3.0)]);
+        let reference = scene(1.0);
+        let error: f32 = fused
+            .iter()
+            .zip(reference.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f32>()
+            / fused.len() as f32;
+        assert!(error < 0.05, "mean error {}", error);
+    }
+}
@@ -0,0 +1,65 @@
+//! Focus stacking, merging frames focused at different distances into one image that's sharp throughout.
+//!
+//! Each pixel is weighted by local sharpness, the Laplacian response averaged over a small neighbourhood,
+//! raised to a power so that whichever frame is sharpest there dominates.
+
+use image::{imageops, Rgb32FImage};
+
+use super::pyramid::{self, WeightMap};
+
+/// Blur applied to the Laplacian response, so sharpness is judged over a neighbourhood rather than per pixel.
+const SHARPNESS_SIGMA: f32 = 2.0;
+
+/// Exponent applied to sharpness before normalizing.
+const SHARPNESS_POWER: i32 = 4;
+
+/// Keeps weights from all being zero in flat areas, which then average the frames.
+const MIN_WEIGHT: f32 = 1e-12;
+
+/// Merge frames of the same scene focused at different distances.
+pub(crate) fn stack_focus(frames: &[Rgb32FImage]) -> Rgb32FImage {
+    let mut weights: Vec<_> = frames.iter().map(sharpness).collect();
+    pyramid::normalize(&mut weights);
+    pyramid::blend(frames, &weights)
+}
+
+fn sharpness(image: &Rgb32FImage) -> WeightMap {
+    let contrast = pyramid::local_contrast(&pyramid::luminance(image));
+    let mut sharpness = imageops::blur(&contrast, SHARPNESS_SIGMA);
+    for weight in sharpness.iter_mut() {
+        *weight = weight.powi(SHARPNESS_POWER) + MIN_WEIGHT;
+    }
+    sharpness
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn mean_error(a: &Rgb32FImage, b: &Rgb32FImage) -> f32 {
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f32>()
+            / a.len() as f32
+    }
+
+    #[test]
+    fn test_takes_sharpest_regions() {
+        let scene = Rgb32FImage::from_fn(64, 64, |x, y| {
+            let v = if (x / 2 + y / 2) % 2 == 0 { 0.2 } else { 0.8 };
+            Rgb([v, v, v])
+        });
+        // Out of focus regions lose all their detail
+        let focused_on = |left: bool| {
+            Rgb32FImage::from_fn(64, 64, |x, y| match (x < 32) == left {
+                true => *scene.get_pixel(x, y),
+                false => Rgb([0.5, 0.5, 0.5]),
+            })
+        };
+        let (near, far) = (focused_on(true), focused_on(false));
+        let stacked = stack_focus(&[near.clone(), far]);
+        assert!(mean_error(&stacked, &scene) < mean_error(&near, &scene) / 2.0);
+    }
+}
@@ -0,0 +1,39 @@
+//! Combining several frames captured at the same pose into a single image.
+//!
+//! Frames are merged in linear-ish `f32` RGB, blending across a Laplacian pyramid so that seams
+//! between regions taken from different frames stay invisible.
+
+mod exposure_fusion;
+mod focus_stack;
+mod pyramid;
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use image::{DynamicImage, Rgb32FImage};
+
+pub(crate) use exposure_fusion::fuse_exposures;
+pub(crate) use focus_stack::stack_focus;
+
+/// Load `frames`, merge them with `merge` and save the result to `output`.
+pub(crate) fn merge_files(
+    frames: &[&Path],
+    merge: fn(&[Rgb32FImage]) -> Rgb32FImage,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let images = frames
+        .iter()
+        .map(|path| Ok(image::open(path)?.to_rgb32f()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let dimensions = images
+        .first()
+        .ok_or(anyhow!("No frames to merge"))?
+        .dimensions();
+    if images.iter().any(|image| image.dimensions() != dimensions) {
+        return Err(anyhow!("Frames to merge differ in size"));
+    }
+    DynamicImage::ImageRgb32F(merge(&images))
+        .to_rgb8()
+        .save(output)?;
+    Ok(())
+}
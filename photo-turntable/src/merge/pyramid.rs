@@ -0,0 +1,193 @@
+//! Gaussian and Laplacian image pyramids, for blending images smoothly across scales.
+
+use image::imageops::{self, FilterType};
+use image::{ImageBuffer, Luma, Pixel, Rgb32FImage};
+
+/// Single channel `f32` image, used for per-pixel weights.
+pub(super) type WeightMap = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// Pyramids stop once the smallest level would be narrower than this.
+const MIN_LEVEL_SIZE: u32 = 8;
+
+/// Number of levels to use for images of the given size.
+pub(super) fn levels(width: u32, height: u32) -> usize {
+    let mut levels = 1;
+    let mut size = width.min(height);
+    while size / 2 >= MIN_LEVEL_SIZE {
+        size /= 2;
+        levels += 1;
+    }
+    levels
+}
+
+fn downsample<P>(image: &ImageBuffer<P, Vec<f32>>) -> ImageBuffer<P, Vec<f32>>
+where
+    P: Pixel<Subpixel = f32> + 'static,
+{
+    let (width, height) = image.dimensions();
+    imageops::resize(
+        image,
+        width.div_ceil(2),
+        height.div_ceil(2),
+        FilterType::Triangle,
+    )
+}
+
+fn upsample<P>(
+    image: &ImageBuffer<P, Vec<f32>>,
+    width: u32,
+    height: u32,
+) -> ImageBuffer<P, Vec<f32>>
+where
+    P: Pixel<Subpixel = f32> + 'static,
+{
+    imageops::resize(image, width, height, FilterType::Triangle)
+}
+
+/// Successively halved copies of `image`, starting with the image itself.
+pub(super) fn gaussian<P>(
+    image: &ImageBuffer<P, Vec<f32>>,
+    levels: usize,
+) -> Vec<ImageBuffer<P, Vec<f32>>>
+where
+    P: Pixel<Subpixel = f32> + 'static,
+{
+    let mut pyramid = vec![image.clone()];
+    for _ in 1..levels {
+        let next = downsample(pyramid.last().unwrap());
+        pyramid.push(next);
+    }
+    pyramid
+}
+
+/// Band-pass detail at each scale, ending with the coarsest Gaussian level.
+pub(super) fn laplacian(image: &Rgb32FImage, levels: usize) -> Vec<Rgb32FImage> {
+    let gaussian = gaussian(image, levels);
+    let mut pyramid: Vec<_> = gaussian
+        .windows(2)
+        .map(|pair| {
+            let (width, height) = pair[0].dimensions();
+            let mut detail = pair[0].clone();
+            let coarse = upsample(&pair[1], width, height);
+            for (d, c) in detail.iter_mut().zip(coarse.iter()) {
+                *d -= c;
+            }
+            detail
+        })
+        .collect();
+    pyramid.push(gaussian.last().unwrap().clone());
+    pyramid
+}
+
+/// Rebuild an image from its Laplacian pyramid.
+pub(super) fn collapse(pyramid: Vec<Rgb32FImage>) -> Rgb32FImage {
+    let mut levels = pyramid.into_iter().rev();
+    let mut image = levels.next().expect("Pyramid has no levels");
+    for mut detail in levels {
+        let (width, height) = detail.dimensions();
+        let coarse = upsample(&image, width, height);
+        for (d, c) in detail.iter_mut().zip(coarse.iter()) {
+            *d += c;
+        }
+        image = detail;
+    }
+    image
+}
+
+/// Luminance of each pixel.
+pub(super) fn luminance(image: &Rgb32FImage) -> WeightMap {
+    WeightMap::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        Luma([0.299 * r + 0.587 * g + 0.114 * b])
+    })
+}
+
+/// Absolute response of a 3x3 Laplacian filter, a measure of local detail.
+pub(super) fn local_contrast(gray: &WeightMap) -> WeightMap {
+    let (width, height) = gray.dimensions();
+    let at = |x: i64, y: i64| {
+        let x = x.clamp(0, width as i64 - 1) as u32;
+        let y = y.clamp(0, height as i64 - 1) as u32;
+        gray.get_pixel(x, y).0[0]
+    };
+    WeightMap::from_fn(width, height, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let laplacian = at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+        Luma([laplacian.abs()])
+    })
+}
+
+/// Scale weights so they sum to one at every pixel.
+pub(super) fn normalize(weights: &mut [WeightMap]) {
+    let Some(first) = weights.first() else {
+        return;
+    };
+    let mut totals = vec![0.0; first.len()];
+    for weight in weights.iter() {
+        for (total, w) in totals.iter_mut().zip(weight.iter()) {
+            *total += w;
+        }
+    }
+    for weight in weights.iter_mut() {
+        for (w, total) in weight.iter_mut().zip(totals.iter()) {
+            *w = match *total {
+                0.0 => 0.0,
+                total => *w / total,
+            };
+        }
+    }
+}
+
+/// Blend images with per-pixel weights summing to one, scale by scale.
+pub(super) fn blend(images: &[Rgb32FImage], weights: &[WeightMap]) -> Rgb32FImage {
+    let (width, height) = images[0].dimensions();
+    let levels = levels(width, height);
+    let mut blended: Option<Vec<Rgb32FImage>> = None;
+    for (image, weight) in images.iter().zip(weights) {
+        let detail = laplacian(image, levels);
+        let weight = gaussian(weight, levels);
+        let weighted = detail.into_iter().zip(&weight).map(|(mut level, weight)| {
+            for (pixel, w) in level.pixels_mut().zip(weight.iter()) {
+                pixel.apply(|channel| channel * w);
+            }
+            level
+        });
+        blended = Some(match blended {
+            None => weighted.collect(),
+            Some(sum) => sum
+                .into_iter()
+                .zip(weighted)
+                .map(|(mut sum, level)| {
+                    for (s, l) in sum.iter_mut().zip(level.iter()) {
+                        *s += l;
+                    }
+                    sum
+                })
+                .collect(),
+        });
+    }
+    let mut image = collapse(blended.expect("No images to blend"));
+    for channel in image.iter_mut() {
+        *channel = channel.clamp(0.0, 1.0);
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_laplacian_round_trip() {
+        let image = Rgb32FImage::from_fn(37, 20, |x, y| {
+            Rgb([x as f32 / 37.0, y as f32 / 20.0, ((x * y) % 7) as f32 / 7.0])
+        });
+        let levels = levels(37, 20);
+        assert_eq!(levels, 2);
+        let rebuilt = collapse(laplacian(&image, levels));
+        for (a, b) in image.iter().zip(rebuilt.iter()) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+}
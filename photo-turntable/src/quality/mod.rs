@@ -0,0 +1,7 @@
+//! Checks on captured images, to catch bad frames while the object is still on the table.
+
+mod motion;
+mod sharpness;
+
+pub(crate) use motion::{difference, unmoved};
+pub(crate) use sharpness::{below_median, sharpness};
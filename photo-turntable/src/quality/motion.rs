@@ -0,0 +1,118 @@
+//! Checking that the table really moved between captures.
+//!
+//! A dropped move command leaves the table where it was, so the next capture shows the same view.
+//! Consecutive previews are compared at a small fixed size, which evens out sensor noise and lets
+//! images of different sizes be compared. How much a view changes between poses depends on the
+//! object's size and the step between poses, so differences are judged against the job's own.
+
+use image::{imageops, RgbaImage};
+
+/// Side of the square both images are shrunk to before comparing.
+const COMPARE_SIZE: u32 = 64;
+
+/// Differences below this are taken to be the same view when there are no differences between poses
+/// to go by. It's little more than sensor noise, so only a table that hasn't moved at all is caught.
+const UNMOVED_DIFFERENCE: f32 = 0.0005;
+
+/// Fraction of the job's median difference between poses below which a capture is taken to show the
+/// same view, rather than the object having turned.
+const UNMOVED_FRACTION: f32 = 0.25;
+
+/// Mean absolute difference in luminance, from 0 for identical images to 1.
+pub(crate) fn difference(a: &RgbaImage, b: &RgbaImage) -> f32 {
+    let shrink = |image: &RgbaImage| {
+        imageops::resize(
+            &imageops::grayscale(image),
+            COMPARE_SIZE,
+            COMPARE_SIZE,
+            imageops::FilterType::Triangle,
+        )
+    };
+    let (a, b) = (shrink(a), shrink(b));
+    let total: u32 = a
+        .pixels()
+        .zip(b.pixels())
+        .map(|(a, b)| a[0].abs_diff(b[0]) as u32)
+        .sum();
+    total as f32 / (255.0 * (COMPARE_SIZE * COMPARE_SIZE) as f32)
+}
+
+/// Whether a capture differing from the previous one by `difference` shows the same view, so the
+/// table can't have moved between them. `moved` holds the differences between earlier poses of the job,
+/// and until there are any, only a near-identical capture is taken to be unmoved.
+pub(crate) fn unmoved(difference: f32, moved: &[f32]) -> bool {
+    let mut moved = moved.to_vec();
+    moved.sort_by(f32::total_cmp);
+    match moved.get(moved.len() / 2) {
+        Some(typical) => difference < UNMOVED_FRACTION * typical,
+        None => difference < UNMOVED_DIFFERENCE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn stripes(offset: u32) -> RgbaImage {
+        RgbaImage::from_fn(200, 100, |x, _| match ((x + offset) / 20) % 2 {
+            0 => Rgba([30, 30, 30, 255]),
+            _ => Rgba([220, 220, 220, 255]),
+        })
+    }
+
+    /// Sensor noise of up to two levels either way.
+    fn add_noise(image: &mut RgbaImage) {
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let noise = ((x * 7 + y * 13) % 5) as i32 - 2;
+            for channel in &mut pixel.0[..3] {
+                *channel = (*channel as i32 + noise).clamp(0, 255) as u8;
+            }
+        }
+    }
+
+    /// A small striped disc in the middle of a plain backdrop, turned by `angle_deg`.
+    fn small_object(angle_deg: f32) -> RgbaImage {
+        let (sin, cos) = angle_deg.to_radians().sin_cos();
+        RgbaImage::from_fn(200, 100, |x, y| {
+            let (dx, dy) = (x as f32 - 100.0, y as f32 - 50.0);
+            if dx * dx + dy * dy > 12.0 * 12.0 {
+                return Rgba([128, 128, 128, 255]);
+            }
+            match ((dx * cos + dy * sin + 100.0) / 3.0) as u32 % 2 {
+                0 => Rgba([30, 30, 30, 255]),
+                _ => Rgba([220, 220, 220, 255]),
+            }
+        })
+    }
+
+    #[test]
+    fn test_unmoved() {
+        let mut noisy = stripes(0);
+        add_noise(&mut noisy);
+        let moved = [difference(&stripes(0), &stripes(10))];
+        assert!(unmoved(difference(&stripes(0), &noisy), &moved));
+        assert!(!unmoved(difference(&stripes(0), &stripes(10)), &moved));
+    }
+
+    #[test]
+    fn test_small_object_turning_a_few_degrees_moved() {
+        let moved: Vec<_> = (0..4)
+            .map(|step| {
+                difference(
+                    &small_object(step as f32 * 4.0),
+                    &small_object((step + 1) as f32 * 4.0),
+                )
+            })
+            .collect();
+        let turned = difference(&small_object(20.0), &small_object(24.0));
+        assert!(!unmoved(turned, &moved));
+        assert!(!unmoved(turned, &[]));
+
+        let mut noisy = small_object(20.0);
+        add_noise(&mut noisy);
+        assert!(unmoved(difference(&small_object(20.0), &noisy), &moved));
+        // Even a job's first move is checked, if only for the table not moving at all
+        assert!(unmoved(difference(&small_object(20.0), &noisy), &[]));
+    }
+}
@@ -0,0 +1,77 @@
+//! Sharpness scoring, to catch motion blur and missed focus.
+//!
+//! The score is the variance of the Laplacian of a grayscale preview. It only means something relative to
+//! other images of the same subject, so images are judged against the median of their peers.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use image::{imageops, RgbaImage};
+
+/// Variance of the 4-neighbour Laplacian of the image's luminance. Higher is sharper.
+pub(crate) fn sharpness(image: &RgbaImage) -> f32 {
+    let gray = imageops::grayscale(image);
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let at = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian =
+                at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+            sum += laplacian;
+            sum_sq += laplacian * laplacian;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    (sum_sq / count - mean * mean) as f32
+}
+
+/// Whether each score is below `ratio` times the median score of those sharing its key,
+/// e.g. frames from the same camera at the same point in a bracket.
+pub(crate) fn below_median<K: Eq + Hash + Copy>(scores: &[(K, f32)], ratio: f32) -> Vec<bool> {
+    let mut peers: HashMap<K, Vec<f32>> = HashMap::new();
+    for &(key, score) in scores {
+        peers.entry(key).or_default().push(score);
+    }
+    let medians: HashMap<K, f32> = peers
+        .into_iter()
+        .map(|(key, mut scores)| {
+            scores.sort_by(f32::total_cmp);
+            (key, scores[scores.len() / 2])
+        })
+        .collect();
+    scores
+        .iter()
+        .map(|(key, score)| *score < ratio * medians[key])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_blur_lowers_sharpness() {
+        let checkerboard = RgbaImage::from_fn(64, 64, |x, y| match (x / 4 + y / 4) % 2 {
+            0 => Rgba([0, 0, 0, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+        let blurred = imageops::blur(&checkerboard, 3.0);
+        assert!(sharpness(&checkerboard) > 10.0 * sharpness(&blurred));
+        assert_eq!(sharpness(&RgbaImage::new(64, 64)), 0.0);
+    }
+
+    #[test]
+    fn test_below_median() {
+        let scores = [(0, 100.0), (0, 90.0), (0, 30.0), (1, 10.0), (1, 12.0)];
+        assert_eq!(
+            below_median(&scores, 0.5),
+            [false, false, true, false, false]
+        );
+    }
+}
@@ -1,12 +1,16 @@
 //! Bluetooth LE transport for the Revopoint Dual Axis Turntable using btleplug.
 
 use anyhow::anyhow;
-use btleplug::api::{Central, Manager as _, Peripheral as _, ScanFilter, WriteType};
+use async_trait::async_trait;
+use btleplug::api::{Central, CharPropFlags, Manager as _, Peripheral as _, ScanFilter, WriteType};
 use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures::StreamExt;
 use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
 use tokio::time::sleep;
 
 use super::command::Command;
+use super::transport::{TrafficKind, TrafficLog, Transport};
 
 /// UUIDs for the Revopoint turntable BLE service and characteristic.
 const TURN_SERVICE_UUID: uuid::Uuid = uuid::Uuid::from_u128(0x0000ffe1_0000_1000_8000_00805f9b34fb);
@@ -16,6 +20,9 @@ const TURN_CHAR_UUID: uuid::Uuid = uuid::Uuid::from_u128(0x0000ffe1_0000_1000_80
 #[derive(Debug)]
 pub struct RevopointBLE {
     peripheral: Peripheral,
+    /// Messages received from the turntable via notifications
+    message_rx: UnboundedReceiver<String>,
+    traffic: TrafficLog,
 }
 
 impl RevopointBLE {
@@ -34,8 +41,8 @@ impl RevopointBLE {
         None
     }
 
-    /// Discover and connect to the first turntable found.
-    pub async fn connect() -> Result<Self, anyhow::Error> {
+    /// Discover and connect to the first turntable found, reporting all messages to `traffic`.
+    pub async fn connect(traffic: TrafficLog) -> Result<Self, anyhow::Error> {
         // Initialize the manager and get the first bluetooth adapter
         let manager = Manager::new().await?;
         let adapter = manager
@@ -58,13 +65,56 @@ impl RevopointBLE {
         turntable.connect().await?;
         turntable.discover_services().await?;
 
+        let message_rx = Self::subscribe(&turntable, traffic.clone()).await?;
         Ok(RevopointBLE {
             peripheral: turntable,
+            message_rx,
+            traffic,
         })
     }
 
+    /// Subscribe to notifications from the turntable, reassembling them into `;`-terminated messages.
+    /// Messages are logged as they arrive, whether or not anything is waiting to receive them.
+    async fn subscribe(
+        turntable: &Peripheral,
+        traffic: TrafficLog,
+    ) -> Result<UnboundedReceiver<String>, anyhow::Error> {
+        let (message_tx, message_rx) = mpsc::unbounded_channel();
+        let chars = turntable.characteristics();
+        let Some(notify_char) = chars
+            .iter()
+            .find(|c| c.uuid == TURN_CHAR_UUID && c.properties.contains(CharPropFlags::NOTIFY))
+        else {
+            eprintln!("Turntable characteristic doesn't support notifications");
+            return Ok(message_rx);
+        };
+        turntable.subscribe(notify_char).await?;
+        let mut notifications = turntable.notifications().await?;
+        tokio::spawn(async move {
+            let mut buffer = String::new();
+            while let Some(notification) = notifications.next().await {
+                buffer.push_str(&String::from_utf8_lossy(&notification.value));
+                while let Some(end) = buffer.find(';') {
+                    let message: String = buffer.drain(..=end).collect();
+                    traffic.record(TrafficKind::Received, &message);
+                    if message_tx.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(message_rx)
+    }
+}
+
+#[async_trait]
+impl Transport for RevopointBLE {
+    fn device_id(&self) -> String {
+        self.peripheral.address().to_string()
+    }
+
     /// Send a command to the turntable over BLE.
-    pub async fn send_command(&self, cmd: &Command) -> Result<(), anyhow::Error> {
+    async fn send_command(&mut self, cmd: &Command) -> Result<(), anyhow::Error> {
         // Locate the characteristic
         let chars = self.peripheral.characteristics();
         let write_char = chars
@@ -73,14 +123,23 @@ impl RevopointBLE {
             .ok_or(anyhow!("Characteristic not found"))?;
 
         let data = cmd.to_string();
+        self.traffic.record(TrafficKind::Sent, &data);
         self.peripheral
             .write(&write_char, data.as_bytes(), WriteType::WithoutResponse)
             .await?;
         Ok(())
     }
 
+    /// Wait for the next message notified by the turntable.
+    async fn receive(&mut self) -> Result<String, anyhow::Error> {
+        self.message_rx
+            .recv()
+            .await
+            .ok_or(anyhow!("Turntable notifications ended"))
+    }
+
     /// Disconnect from the peripheral.
-    pub async fn disconnect(&self) -> Result<(), anyhow::Error> {
+    async fn disconnect(&mut self) -> Result<(), anyhow::Error> {
         self.peripheral.disconnect().await?;
         Ok(())
     }
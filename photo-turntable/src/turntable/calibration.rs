@@ -0,0 +1,172 @@
+//! Move-time models for turntables which can't report when a move has finished.
+//!
+//! Each axis is modelled as a fixed overhead plus a per-degree term, fitted from timed moves
+//! and stored per device.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// One of the turntable's two axes of motion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Rotation,
+    Tilt,
+}
+
+/// Time taken by a move on one axis: `constant_ms + ms_per_deg * degrees`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisTiming {
+    pub constant_ms: f32,
+    pub ms_per_deg: f32,
+}
+
+impl AxisTiming {
+    /// Estimated time for a move of `degrees` (in either direction).
+    pub fn estimate(&self, degrees: f32) -> Duration {
+        Duration::from_secs_f32(
+            (self.constant_ms + self.ms_per_deg * degrees.abs()).max(0.0) / 1000.0,
+        )
+    }
+
+    /// Timing when moving `factor` times slower than this was measured at.
+    /// Only the per-degree term is affected.
+    pub fn slowed_by(&self, factor: f32) -> Self {
+        AxisTiming {
+            constant_ms: self.constant_ms,
+            ms_per_deg: self.ms_per_deg * factor,
+        }
+    }
+
+    /// Least-squares fit to `(degrees, duration)` samples.
+    /// Returns `None` unless there are at least two distinct move sizes, and moves take longer the further they go.
+    pub fn fit(samples: &[(f32, Duration)]) -> Option<Self> {
+        let n = samples.len() as f64;
+        let points = samples
+            .iter()
+            .map(|(degrees, duration)| (degrees.abs() as f64, duration.as_secs_f64() * 1000.0));
+        let (sum_x, sum_y) = points
+            .clone()
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+        let (sxx, sxy) = points.fold((0.0, 0.0), |(sxx, sxy), (x, y)| {
+            (
+                sxx + (x - mean_x).powi(2),
+                sxy + (x - mean_x) * (y - mean_y),
+            )
+        });
+        if sxx == 0.0 || sxy <= 0.0 {
+            return None;
+        }
+        let ms_per_deg = sxy / sxx;
+        Some(AxisTiming {
+            constant_ms: (mean_y - ms_per_deg * mean_x) as f32,
+            ms_per_deg: ms_per_deg as f32,
+        })
+    }
+}
+
+/// Move-time models for both axes of a turntable.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MotionTiming {
+    pub rotation: AxisTiming,
+    pub tilt: AxisTiming,
+}
+
+impl MotionTiming {
+    pub fn axis(&self, axis: Axis) -> &AxisTiming {
+        match axis {
+            Axis::Rotation => &self.rotation,
+            Axis::Tilt => &self.tilt,
+        }
+    }
+
+    pub fn axis_mut(&mut self, axis: Axis) -> &mut AxisTiming {
+        match axis {
+            Axis::Rotation => &mut self.rotation,
+            Axis::Tilt => &mut self.tilt,
+        }
+    }
+
+    /// Directory calibrations are stored in, under the user's config directory.
+    pub fn default_dir() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("photo-turntable")
+                .join("calibration"),
+        )
+    }
+
+    /// Where calibration for a device is stored within `dir`.
+    fn path(dir: &Path, device_id: &str) -> PathBuf {
+        let file_name: String = device_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        dir.join(file_name).with_extension("json")
+    }
+
+    /// Load the calibration saved in `dir` for a device, if there is one.
+    pub fn load(dir: &Path, device_id: &str) -> Option<Self> {
+        let data = std::fs::read(Self::path(dir, device_id)).ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(timing) => Some(timing),
+            Err(e) => {
+                eprintln!("Ignoring unreadable calibration for {}: {:?}", device_id, e);
+                None
+            }
+        }
+    }
+
+    /// Save this calibration for a device in `dir`.
+    pub fn save(&self, dir: &Path, device_id: &str) -> Result<(), anyhow::Error> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(Self::path(dir, device_id), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit() {
+        let samples: Vec<_> = [10.0, 30.0, 90.0, 180.0]
+            .into_iter()
+            .map(|deg: f32| (deg, Duration::from_secs_f32((250.0 + 100.0 * deg) / 1000.0)))
+            .collect();
+        let timing = AxisTiming::fit(&samples).unwrap();
+        assert!((timing.constant_ms - 250.0).abs() < 0.5);
+        assert!((timing.ms_per_deg - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_fit_needs_distinct_moves() {
+        let samples = [
+            (10.0, Duration::from_secs(1)),
+            (10.0, Duration::from_secs(2)),
+        ];
+        assert_eq!(AxisTiming::fit(&samples), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("calibration-test-{}", std::process::id()));
+        let timing = MotionTiming {
+            rotation: AxisTiming {
+                constant_ms: 300.0,
+                ms_per_deg: 90.0,
+            },
+            tilt: AxisTiming {
+                constant_ms: 200.0,
+                ms_per_deg: 40.0,
+            },
+        };
+        assert_eq!(MotionTiming::load(&dir, "AA:BB"), None);
+        timing.save(&dir, "AA:BB").unwrap();
+        assert!(dir.join("AA_BB.json").exists());
+        assert_eq!(MotionTiming::load(&dir, "AA:BB"), Some(timing));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
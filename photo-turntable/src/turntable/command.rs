@@ -1,6 +1,8 @@
+/// Angles are sent with two decimal places, so the turntable can only be commanded in multiples of this.
+pub const ANGLE_RESOLUTION_DEG: f64 = 0.01;
+
 /// Represents a command to send to the turntable.
 #[derive(Debug, Clone, PartialEq)]
-#[allow(dead_code)]
 pub enum Command {
     /// Set rotation speed in units understood by device (e.g., 35.64 to 131)
     SetRotationSpeed(f32),
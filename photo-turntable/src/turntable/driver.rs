@@ -0,0 +1,107 @@
+//! Registry of turntable drivers which can be selected at runtime.
+
+use super::ble::RevopointBLE;
+use super::calibration::MotionTiming;
+use super::grbl::{GrblConfig, GrblTurntable};
+use super::serial::{self, RevopointSerial};
+use super::simulated::{self, SimulatedTurntable};
+use super::transport::TrafficLog;
+use super::{RevoTurntable, SpeedLimits, Turntable, REVO_MAX_TILT_DEG, REVO_SPEED_LIMITS};
+
+/// A turntable driver, along with the settings needed to connect using it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DriverSettings {
+    /// Revopoint Dual Axis Turntable over Bluetooth LE.
+    #[default]
+    RevopointBle,
+    /// Revopoint Dual Axis Turntable over a UART bridge or BLE-to-serial dongle.
+    /// An empty port selects the first serial port found.
+    RevopointSerial { port: String, baud_rate: u32 },
+    /// Stepper turntable running GRBL over USB serial.
+    /// An empty port selects the first serial port found.
+    Grbl { port: String, config: GrblConfig },
+    /// Simulated turntable which drives no hardware. Top speeds are in degrees/second.
+    Simulated {
+        rotation_speed: f32,
+        tilt_speed: f32,
+    },
+}
+
+impl DriverSettings {
+    /// Default settings for every available driver, in display order.
+    pub fn registry() -> Vec<DriverSettings> {
+        vec![
+            DriverSettings::RevopointBle,
+            DriverSettings::RevopointSerial {
+                port: String::new(),
+                baud_rate: serial::DEFAULT_BAUD_RATE,
+            },
+            DriverSettings::Grbl {
+                port: String::new(),
+                config: GrblConfig::default(),
+            },
+            DriverSettings::Simulated {
+                rotation_speed: 30.0,
+                tilt_speed: 10.0,
+            },
+        ]
+    }
+
+    /// Display name of the driver.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DriverSettings::RevopointBle => "Revopoint BLE",
+            DriverSettings::RevopointSerial { .. } => "Revopoint serial",
+            DriverSettings::Grbl { .. } => "GRBL serial",
+            DriverSettings::Simulated { .. } => "Simulated",
+        }
+    }
+
+    /// Speeds a job may drive the turntable at.
+    pub fn speed_limits(&self) -> SpeedLimits {
+        match self {
+            DriverSettings::RevopointBle | DriverSettings::RevopointSerial { .. } => {
+                REVO_SPEED_LIMITS
+            }
+            DriverSettings::Grbl { config, .. } => config.speed_limits(),
+            DriverSettings::Simulated {
+                rotation_speed,
+                tilt_speed,
+            } => SimulatedTurntable::speed_limits(*rotation_speed, *tilt_speed),
+        }
+    }
+
+    /// Furthest a table using this driver tilts either side of level, matching
+    /// [`Turntable::max_tilt_deg`] once connected.
+    pub fn max_tilt_deg(&self) -> f32 {
+        match self {
+            DriverSettings::RevopointBle | DriverSettings::RevopointSerial { .. } => {
+                REVO_MAX_TILT_DEG
+            }
+            DriverSettings::Grbl { config, .. } => config.max_tilt_deg,
+            DriverSettings::Simulated { .. } => simulated::MAX_TILT_DEG,
+        }
+    }
+
+    /// Connect to a turntable using this driver.
+    /// Drivers speaking the Revopoint protocol report their messages to `traffic`.
+    pub async fn connect(&self, traffic: TrafficLog) -> Result<Box<dyn Turntable>, anyhow::Error> {
+        Ok(match self {
+            DriverSettings::RevopointBle => Box::new(RevoTurntable::new(
+                RevopointBLE::connect(traffic).await?,
+                MotionTiming::default_dir(),
+            )),
+            DriverSettings::RevopointSerial { port, baud_rate } => Box::new(RevoTurntable::new(
+                RevopointSerial::open(port, *baud_rate, traffic)?,
+                MotionTiming::default_dir(),
+            )),
+            DriverSettings::Grbl { port, config } => {
+                Box::new(GrblTurntable::open(port, config.clone()).await?)
+            }
+            DriverSettings::Simulated {
+                rotation_speed,
+                tilt_speed,
+            } => Box::new(SimulatedTurntable::new(*rotation_speed, *tilt_speed)),
+        })
+    }
+}
@@ -0,0 +1,548 @@
+//! G-code driver for DIY stepper turntables running GRBL over USB serial.
+//!
+//! Rotation and tilt are mapped onto GRBL axes, with the axis "steps/mm" setting used as steps/degree,
+//! so one unit of travel is one degree. Moves are confirmed by waiting for GRBL's `ok` response,
+//! then polling status reports until the machine is idle.
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::time::{sleep, timeout};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+use super::serial;
+use super::{MotionSpeeds, Pose, SpeedLimits, Turntable};
+
+/// How long to wait for the startup banner after opening the port (most boards reset on connect).
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(3);
+/// Interval between `?` status queries while waiting for a move to complete.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Distance of the jog used for continuous rotation, far enough to never finish by itself.
+const CONTINUOUS_JOG_DEG: f32 = 1_000_000.0;
+/// Realtime command cancelling a jog in progress.
+const JOG_CANCEL: u8 = 0x85;
+/// Slowest feed rate a job may request, in degrees/minute.
+const MIN_FEED_RATE: f32 = 1.0;
+
+/// Machine configuration for a GRBL turntable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrblConfig {
+    pub baud_rate: u32,
+    /// G-code axis driving rotation (`X`, `Y` or `Z`).
+    pub rotation_axis: char,
+    /// G-code axis driving tilt (`X`, `Y` or `Z`).
+    pub tilt_axis: char,
+    pub rotation_steps_per_degree: f32,
+    pub tilt_steps_per_degree: f32,
+    /// Fastest rotation feed rate in degrees/minute, used unless a job asks for slower.
+    pub rotation_feed_rate: f32,
+    /// Fastest tilt feed rate in degrees/minute, used unless a job asks for slower.
+    pub tilt_feed_rate: f32,
+    /// Run a `$H` homing cycle when configuring and resetting.
+    pub homing: bool,
+    /// Tilt angle of the table when sitting at the homing switch.
+    pub tilt_home_deg: f32,
+    /// Furthest the table tilts either side of level, in degrees.
+    pub max_tilt_deg: f32,
+}
+
+impl Default for GrblConfig {
+    fn default() -> Self {
+        // 1.8 degree steppers at 1/16 microstepping, direct drive
+        let steps_per_degree = 200.0 * 16.0 / 360.0;
+        Self {
+            baud_rate: 115200,
+            rotation_axis: 'X',
+            tilt_axis: 'Y',
+            rotation_steps_per_degree: steps_per_degree,
+            tilt_steps_per_degree: steps_per_degree,
+            rotation_feed_rate: 1800.0,
+            tilt_feed_rate: 600.0,
+            homing: false,
+            tilt_home_deg: 0.0,
+            max_tilt_deg: 90.0,
+        }
+    }
+}
+
+impl GrblConfig {
+    /// GRBL's `$10x` steps/mm setting number for an axis.
+    fn steps_setting(axis: char) -> Result<u8, anyhow::Error> {
+        match axis {
+            'X' => Ok(100),
+            'Y' => Ok(101),
+            'Z' => Ok(102),
+            other => Err(anyhow!("Unsupported GRBL axis {:?}", other)),
+        }
+    }
+
+    /// Speeds the table can be driven at, up to the configured feed rates.
+    pub fn speed_limits(&self) -> SpeedLimits {
+        SpeedLimits {
+            slowest: MotionSpeeds {
+                rotation_deg_per_sec: MIN_FEED_RATE / 60.0,
+                tilt_deg_per_sec: MIN_FEED_RATE / 60.0,
+            },
+            fastest: MotionSpeeds {
+                rotation_deg_per_sec: self.rotation_feed_rate / 60.0,
+                tilt_deg_per_sec: self.tilt_feed_rate / 60.0,
+            },
+        }
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        Self::steps_setting(self.rotation_axis)?;
+        Self::steps_setting(self.tilt_axis)?;
+        if self.rotation_axis == self.tilt_axis {
+            return Err(anyhow!("Rotation and tilt must use different axes"));
+        }
+        if self.rotation_steps_per_degree <= 0.0 || self.tilt_steps_per_degree <= 0.0 {
+            return Err(anyhow!("Steps per degree must be positive"));
+        }
+        if self.max_tilt_deg <= 0.0 {
+            return Err(anyhow!("Max tilt must be positive"));
+        }
+        if self.rotation_feed_rate < MIN_FEED_RATE || self.tilt_feed_rate < MIN_FEED_RATE {
+            return Err(anyhow!(
+                "Feed rates must be at least {} deg/min",
+                MIN_FEED_RATE
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Stepper turntable controlled by GRBL.
+#[derive(Debug)]
+pub struct GrblTurntable {
+    port: BufReader<SerialStream>,
+    config: GrblConfig,
+    speeds: MotionSpeeds,
+    /// Tracked position, or `None` until homed (if homing is enabled) or after a stop.
+    /// Rotation is tracked relative to the last reset, as it is only ever commanded relatively.
+    position: Option<Pose>,
+    /// Machine position of the rotation axis when a continuous rotation was started
+    continuous_rotation_from: Option<f32>,
+}
+
+impl GrblTurntable {
+    /// Open the GRBL controller on the serial port at `path`, or the first serial port found if `path` is empty.
+    pub async fn open(path: &str, config: GrblConfig) -> Result<Self, anyhow::Error> {
+        config.validate()?;
+        let port = tokio_serial::new(serial::resolve_port_path(path)?, config.baud_rate)
+            .open_native_async()?;
+        let mut table = Self::new(port, config);
+        table.wait_for_startup().await?;
+        Ok(table)
+    }
+
+    fn new(port: SerialStream, config: GrblConfig) -> Self {
+        Self {
+            port: BufReader::new(port),
+            position: if config.homing {
+                None
+            } else {
+                Some(Pose::default())
+            },
+            speeds: config.speed_limits().fastest,
+            continuous_rotation_from: None,
+            config,
+        }
+    }
+
+    fn rotation_feed_rate(&self) -> f32 {
+        self.speeds.rotation_deg_per_sec * 60.0
+    }
+
+    fn tilt_feed_rate(&self) -> f32 {
+        self.speeds.tilt_deg_per_sec * 60.0
+    }
+
+    /// Wait for the `Grbl x.y` banner. Boards which don't reset on connect won't send one.
+    async fn wait_for_startup(&mut self) -> Result<(), anyhow::Error> {
+        let banner = timeout(STARTUP_TIMEOUT, async {
+            loop {
+                let line = self.read_line().await?;
+                if line.starts_with("Grbl") {
+                    return Ok::<_, anyhow::Error>(line);
+                }
+            }
+        })
+        .await;
+        match banner {
+            Ok(line) => eprintln!("Connected to {}", line?),
+            Err(_) => eprintln!("No GRBL startup banner received, continuing anyway"),
+        }
+        Ok(())
+    }
+
+    /// Read a single response line, without its line ending.
+    async fn read_line(&mut self) -> Result<String, anyhow::Error> {
+        let mut line = String::new();
+        if self.port.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("GRBL serial port closed"));
+        }
+        Ok(line.trim().to_string())
+    }
+
+    /// Send a line of G-code (or a `$` command), and wait for it to be acknowledged.
+    async fn send_line(&mut self, line: &str) -> Result<(), anyhow::Error> {
+        self.port
+            .write_all(format!("{}\n", line).as_bytes())
+            .await?;
+        self.port.flush().await?;
+        loop {
+            let response = self.read_line().await?;
+            if response == "ok" {
+                return Ok(());
+            }
+            if let Some(code) = response.strip_prefix("error:") {
+                return Err(anyhow!("GRBL rejected {:?} with error {}", line, code));
+            }
+            if let Some(code) = response.strip_prefix("ALARM:") {
+                return Err(anyhow!("GRBL alarm {} while running {:?}", code, line));
+            }
+            // Anything else is a banner, message or stray status report
+        }
+    }
+
+    /// Request a status report, and return its fields.
+    async fn query_status(&mut self) -> Result<Vec<String>, anyhow::Error> {
+        // `?` is a realtime command: no newline, and answered with a status report rather than `ok`
+        self.port.write_all(b"?").await?;
+        self.port.flush().await?;
+        loop {
+            let line = self.read_line().await?;
+            // e.g. `<Idle|MPos:0.000,0.000,0.000|FS:0,0>`
+            if let Some(report) = line.strip_prefix('<') {
+                return Ok(report
+                    .trim_end_matches('>')
+                    .split('|')
+                    .map(str::to_string)
+                    .collect());
+            }
+        }
+    }
+
+    /// Request a status report, and return the machine state (e.g. `Idle`, `Run` or `Hold:0`).
+    async fn query_state(&mut self) -> Result<String, anyhow::Error> {
+        Ok(self.query_status().await?.swap_remove(0))
+    }
+
+    /// Request a status report, and return the reported position of an axis.
+    /// This is in machine or work coordinates depending on GRBL's `$10` setting, so is only
+    /// useful for measuring distance travelled.
+    async fn query_axis_position(&mut self, axis: char) -> Result<f32, anyhow::Error> {
+        let index = match axis {
+            'X' => 0,
+            'Y' => 1,
+            'Z' => 2,
+            other => return Err(anyhow!("Unsupported GRBL axis {:?}", other)),
+        };
+        let status = self.query_status().await?;
+        let coordinates = status
+            .iter()
+            .find_map(|field| {
+                field
+                    .strip_prefix("MPos:")
+                    .or_else(|| field.strip_prefix("WPos:"))
+            })
+            .ok_or(anyhow!("GRBL status report has no position"))?;
+        Ok(coordinates
+            .split(',')
+            .nth(index)
+            .ok_or(anyhow!("GRBL status report has no {} position", axis))?
+            .parse()?)
+    }
+
+    /// Poll status reports until the machine reports `Idle`.
+    async fn wait_idle(&mut self) -> Result<(), anyhow::Error> {
+        loop {
+            let state = self.query_state().await?;
+            if state == "Idle" {
+                return Ok(());
+            }
+            if state.starts_with("Alarm") {
+                return Err(anyhow!("GRBL is in alarm state"));
+            }
+            sleep(STATUS_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Run a single-axis feed move and wait for it to complete.
+    /// `mode` is `G90` for absolute or `G91` for relative positioning.
+    async fn move_axis(
+        &mut self,
+        mode: &str,
+        axis: char,
+        value: f32,
+        feed_rate: f32,
+    ) -> Result<(), anyhow::Error> {
+        self.send_line(&format!(
+            "{} G1 {}{:.3} F{:.1}",
+            mode, axis, value, feed_rate
+        ))
+        .await?;
+        self.wait_idle().await
+    }
+
+    /// Home the machine, and set the work coordinates of the home position.
+    async fn home(&mut self) -> Result<(), anyhow::Error> {
+        self.send_line("$H").await?;
+        self.wait_idle().await?;
+        self.send_line(&format!(
+            "G92 {}0 {}{:.3}",
+            self.config.rotation_axis, self.config.tilt_axis, self.config.tilt_home_deg
+        ))
+        .await?;
+        self.position = Some(Pose {
+            rotation_deg: 0.0,
+            tilt_deg: self.config.tilt_home_deg,
+        });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Turntable for GrblTurntable {
+    async fn disconnect(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn configure(&mut self) -> Result<(), anyhow::Error> {
+        for (axis, steps_per_degree) in [
+            (
+                self.config.rotation_axis,
+                self.config.rotation_steps_per_degree,
+            ),
+            (self.config.tilt_axis, self.config.tilt_steps_per_degree),
+        ] {
+            let setting = GrblConfig::steps_setting(axis)?;
+            self.send_line(&format!("${}={:.3}", setting, steps_per_degree))
+                .await?;
+        }
+        // Millimetre mode, so that one unit of travel is one degree
+        self.send_line("G21").await?;
+        if self.config.homing {
+            self.home().await?;
+        }
+        Ok(())
+    }
+
+    async fn reset_pos(&mut self) -> Result<(), anyhow::Error> {
+        if self.config.homing {
+            return self.home().await;
+        }
+        match self.position {
+            Some(position) => {
+                // Rotation is periodic, so take the shortest way back to a whole turn
+                let unwind = -(position.rotation_deg + 180.0).rem_euclid(360.0) + 180.0;
+                if unwind != 0.0 {
+                    let axis = self.config.rotation_axis;
+                    self.move_axis("G91", axis, unwind, self.rotation_feed_rate())
+                        .await?;
+                }
+            }
+            None => {
+                // Without homing switches, the rotation the table stopped at becomes home.
+                // Clear any alarm left by stopping mid-move first.
+                self.send_line("$X").await?;
+            }
+        }
+        let axis = self.config.tilt_axis;
+        self.move_axis("G90", axis, 0.0, self.tilt_feed_rate())
+            .await?;
+        self.position = Some(Pose::default());
+        Ok(())
+    }
+
+    async fn move_to(&mut self, rotation_deg: f32, tilt_deg: f32) -> Result<(), anyhow::Error> {
+        let from = self
+            .position
+            .ok_or(anyhow!("Turntable position unknown, reset position first"))?;
+        let rotation = rotation_deg - from.rotation_deg;
+        if rotation != 0.0 {
+            let axis = self.config.rotation_axis;
+            self.move_axis("G91", axis, rotation, self.rotation_feed_rate())
+                .await?;
+        }
+        self.position = Some(Pose {
+            rotation_deg,
+            tilt_deg: from.tilt_deg,
+        });
+        if tilt_deg != from.tilt_deg {
+            let axis = self.config.tilt_axis;
+            self.move_axis("G90", axis, tilt_deg, self.tilt_feed_rate())
+                .await?;
+        }
+        self.position = Some(Pose {
+            rotation_deg,
+            tilt_deg,
+        });
+        Ok(())
+    }
+
+    fn position(&self) -> Option<Pose> {
+        self.position
+    }
+
+    fn max_tilt_deg(&self) -> f32 {
+        self.config.max_tilt_deg
+    }
+
+    async fn stop(&mut self) -> Result<(), anyhow::Error> {
+        self.position = None;
+        // Feed hold decelerates without losing steps; once held, a soft reset flushes queued moves
+        self.port.write_all(b"!").await?;
+        self.port.flush().await?;
+        loop {
+            let state = self.query_state().await?;
+            if state == "Idle" || state == "Hold:0" || state.starts_with("Alarm") {
+                break;
+            }
+            sleep(STATUS_POLL_INTERVAL).await;
+        }
+        self.port.write_all(&[0x18]).await?;
+        self.port.flush().await?;
+        self.wait_for_startup().await
+    }
+
+    async fn start_continuous_rotation(&mut self, direction: i8) -> Result<(), anyhow::Error> {
+        let axis = self.config.rotation_axis;
+        let from = self.query_axis_position(axis).await?;
+        // A jog can be cancelled cleanly, unlike a feed move
+        self.send_line(&format!(
+            "$J=G91 {}{:.3} F{:.1}",
+            axis,
+            direction.signum() as f32 * CONTINUOUS_JOG_DEG,
+            self.rotation_feed_rate()
+        ))
+        .await?;
+        self.continuous_rotation_from = Some(from);
+        Ok(())
+    }
+
+    async fn stop_continuous_rotation(&mut self) -> Result<(), anyhow::Error> {
+        self.port.write_all(&[JOG_CANCEL]).await?;
+        self.port.flush().await?;
+        self.wait_idle().await?;
+        if let Some(from) = self.continuous_rotation_from.take() {
+            let to = self.query_axis_position(self.config.rotation_axis).await?;
+            if let Some(position) = self.position.as_mut() {
+                position.rotation_deg += to - from;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_speeds(&mut self, speeds: MotionSpeeds) -> Result<(), anyhow::Error> {
+        self.config.speed_limits().validate(&speeds)?;
+        self.speeds = speeds;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::serial::fake::FakeDevice;
+    use super::*;
+
+    /// Acknowledge a line of G-code, then report `Run` once before going `Idle`.
+    async fn complete_move(device: &mut FakeDevice, expected: &str) {
+        assert_eq!(device.recv_until(b'\n').await.unwrap(), expected);
+        device.send("ok\r\n").await.unwrap();
+        device.recv_until(b'?').await.unwrap();
+        device
+            .send("<Run|MPos:1.000,0.000,0.000|FS:1800,0>\r\n")
+            .await
+            .unwrap();
+        device.recv_until(b'?').await.unwrap();
+        device
+            .send("<Idle|MPos:2.000,0.000,0.000|FS:0,0>\r\n")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_moves_wait_for_idle() {
+        let (mut device, port) = FakeDevice::pair_stream().unwrap();
+        let mut table = GrblTurntable::new(port, GrblConfig::default());
+        let host = async {
+            table.move_to(90.0, 0.0).await.unwrap();
+            table.move_to(90.0, -10.0).await.unwrap();
+            table.reset_pos().await.unwrap();
+        };
+        let device = async {
+            complete_move(&mut device, "G91 G1 X90.000 F1800.0\n").await;
+            complete_move(&mut device, "G90 G1 Y-10.000 F600.0\n").await;
+            complete_move(&mut device, "G91 G1 X-90.000 F1800.0\n").await;
+            complete_move(&mut device, "G90 G1 Y0.000 F600.0\n").await;
+        };
+        tokio::join!(host, device);
+        assert_eq!(table.position(), Some(Pose::default()));
+    }
+
+    #[tokio::test]
+    async fn test_continuous_rotation() {
+        let (mut device, port) = FakeDevice::pair_stream().unwrap();
+        let mut table = GrblTurntable::new(port, GrblConfig::default());
+        let host = async {
+            table.start_continuous_rotation(-1).await.unwrap();
+            table.stop_continuous_rotation().await.unwrap();
+        };
+        let device = async {
+            device.recv_until(b'?').await.unwrap();
+            device
+                .send("<Idle|MPos:10.000,0.000,0.000|FS:0,0>\r\n")
+                .await
+                .unwrap();
+            assert_eq!(
+                device.recv_until(b'\n').await.unwrap(),
+                "$J=G91 X-1000000.000 F1800.0\n"
+            );
+            device.send("ok\r\n").await.unwrap();
+            assert_eq!(device.recv_byte().await.unwrap(), JOG_CANCEL);
+            for _ in 0..2 {
+                device.recv_until(b'?').await.unwrap();
+                device
+                    .send("<Idle|MPos:-35.500,0.000,0.000|FS:0,0>\r\n")
+                    .await
+                    .unwrap();
+            }
+        };
+        tokio::join!(host, device);
+        assert_eq!(table.position().unwrap().rotation_deg, -45.5);
+    }
+
+    #[tokio::test]
+    async fn test_error_response() {
+        let (mut device, port) = FakeDevice::pair_stream().unwrap();
+        let mut table = GrblTurntable::new(port, GrblConfig::default());
+        let (result, _) = tokio::join!(table.move_to(45.0, 0.0), async {
+            device.recv_until(b'\n').await.unwrap();
+            device.send("error:9\r\n").await.unwrap();
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(GrblConfig::default().validate().is_ok());
+        let same_axis = GrblConfig {
+            tilt_axis: 'X',
+            ..Default::default()
+        };
+        assert!(same_axis.validate().is_err());
+        let bad_axis = GrblConfig {
+            rotation_axis: 'A',
+            ..Default::default()
+        };
+        assert!(bad_axis.validate().is_err());
+        let no_tilt = GrblConfig {
+            max_tilt_deg: 0.0,
+            ..Default::default()
+        };
+        assert!(no_tilt.validate().is_err());
+    }
+}
@@ -1,93 +1,508 @@
 mod ble;
+pub mod calibration;
 mod command;
+mod driver;
+mod grbl;
+mod serial;
+mod simulated;
+mod transport;
 
-use crate::turntable::command::Command;
-use std::cmp::max;
+pub use driver::DriverSettings;
+pub use transport::{Traffic, TrafficKind, TrafficLog};
+
+use crate::turntable::calibration::{Axis, AxisTiming, MotionTiming};
+use crate::turntable::command::{Command, ANGLE_RESOLUTION_DEG};
+use crate::turntable::transport::Transport;
+use anyhow::anyhow;
+use async_trait::async_trait;
 use std::fmt::Debug;
+use std::path::PathBuf;
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout, Instant};
+
+/// Rotation pace is the time for a full turn, in seconds. The device accepts 35.64 (fastest) to 131.
+const ROTATION_PACE_RANGE: (f32, f32) = (35.64, 131.0);
+/// Tilt pace accepted by the device, from 9 (fastest) to 35.
+const TILT_PACE_RANGE: (f32, f32) = (9.0, 35.0);
+/// Tilt speed in degrees/second multiplied by pace. Measured as 60 degrees in 7 seconds at pace 9,
+/// assuming tilt time scales with pace as rotation does.
+const TILT_PACE_DEG_PER_SEC: f32 = 60.0 / 7.0 * 9.0;
+
+/// Speeds the Revopoint table can be driven at.
+pub(crate) const REVO_SPEED_LIMITS: SpeedLimits = SpeedLimits {
+    slowest: MotionSpeeds {
+        rotation_deg_per_sec: 360.0 / ROTATION_PACE_RANGE.1,
+        tilt_deg_per_sec: TILT_PACE_DEG_PER_SEC / TILT_PACE_RANGE.1,
+    },
+    fastest: MotionSpeeds {
+        rotation_deg_per_sec: 360.0 / ROTATION_PACE_RANGE.0,
+        tilt_deg_per_sec: TILT_PACE_DEG_PER_SEC / TILT_PACE_RANGE.0,
+    },
+};
+/// Furthest the Revopoint table tilts either side of level.
+const REVO_MAX_TILT_DEG: f32 = 30.0;
+/// How long to wait for replies to a raw message.
+const RAW_REPLY_WINDOW: Duration = Duration::from_millis(500);
+/// How long to wait for the reply to an angle query.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Move-time model for tables which haven't been calibrated, tuned for one unit at full speed.
+const DEFAULT_TIMING: MotionTiming = MotionTiming {
+    rotation: AxisTiming {
+        constant_ms: 0.0,
+        ms_per_deg: 1000.0 / REVO_SPEED_LIMITS.fastest.rotation_deg_per_sec,
+    },
+    tilt: AxisTiming {
+        constant_ms: 0.0,
+        ms_per_deg: 1000.0 / REVO_SPEED_LIMITS.fastest.tilt_deg_per_sec,
+    },
+};
+
+/// Absolute position of both turntable axes, in degrees.
+///
+/// Rotation is continuous rather than wrapped to a single turn:
+/// moving from 350 to 370 degrees turns the table forwards by 20 degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Pose {
+    pub rotation_deg: f32,
+    pub tilt_deg: f32,
+}
+
+/// Speeds of both turntable axes, in degrees/second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionSpeeds {
+    pub rotation_deg_per_sec: f32,
+    pub tilt_deg_per_sec: f32,
+}
+
+/// Range of speeds a turntable can be driven at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedLimits {
+    pub slowest: MotionSpeeds,
+    pub fastest: MotionSpeeds,
+}
 
-const ROTATION_PACE: f32 = 35.64;
-const TILT_PACE: f32 = 9.00;
+impl SpeedLimits {
+    /// Check both axis speeds are within the limits.
+    pub fn validate(&self, speeds: &MotionSpeeds) -> Result<(), anyhow::Error> {
+        for (name, speed, slowest, fastest) in [
+            (
+                "Rotation",
+                speeds.rotation_deg_per_sec,
+                self.slowest.rotation_deg_per_sec,
+                self.fastest.rotation_deg_per_sec,
+            ),
+            (
+                "Tilt",
+                speeds.tilt_deg_per_sec,
+                self.slowest.tilt_deg_per_sec,
+                self.fastest.tilt_deg_per_sec,
+            ),
+        ] {
+            if !(slowest..=fastest).contains(&speed) {
+                return Err(anyhow!(
+                    "{} speed {:.2} deg/s is outside the supported range {:.2} to {:.2} deg/s",
+                    name,
+                    speed,
+                    slowest,
+                    fastest
+                ));
+            }
+        }
+        Ok(())
+    }
 
+    /// Bring both axis speeds within the limits.
+    pub fn clamp(&self, speeds: MotionSpeeds) -> MotionSpeeds {
+        MotionSpeeds {
+            rotation_deg_per_sec: speeds.rotation_deg_per_sec.clamp(
+                self.slowest.rotation_deg_per_sec,
+                self.fastest.rotation_deg_per_sec,
+            ),
+            tilt_deg_per_sec: speeds
+                .tilt_deg_per_sec
+                .clamp(self.slowest.tilt_deg_per_sec, self.fastest.tilt_deg_per_sec),
+        }
+    }
+}
+
+/// Revopoint Dual Axis Turntable, driven over any [`Transport`].
+///
+/// The table doesn't report when moves complete, so move durations are estimated from a
+/// per-device [`MotionTiming`] model, scaled by how much slower than full speed the table is set to.
 #[derive(Debug)]
-pub struct RevoTurntable {
-    ble: ble::RevopointBLE,
+pub struct RevoTurntable<T: Transport> {
+    transport: T,
+    /// Move timing at full speed
+    timing: MotionTiming,
+    speeds: MotionSpeeds,
+    /// Where calibrations are loaded from and saved to, if anywhere
+    calibration_dir: Option<PathBuf>,
+    /// Target position, or `None` until the table has been reset.
+    position: Option<Pose>,
+    /// Sum of all rotations actually sent since the last reset, after rounding to the protocol's resolution.
+    /// Relative moves are computed against this, so rounding errors can't accumulate.
+    commanded_rotation_deg: f64,
+    /// Direction and start time of any continuous rotation in progress
+    continuous_rotation: Option<(i8, Instant)>,
+}
+
+impl<T: Transport> RevoTurntable<T> {
+    /// Wrap a connected transport, using the device's calibration saved in `calibration_dir` if there
+    /// is one.
+    pub fn new(transport: T, calibration_dir: Option<PathBuf>) -> Self {
+        let timing = calibration_dir
+            .as_deref()
+            .and_then(|dir| MotionTiming::load(dir, &transport.device_id()))
+            .unwrap_or(DEFAULT_TIMING);
+        Self {
+            transport,
+            timing,
+            calibration_dir,
+            speeds: REVO_SPEED_LIMITS.fastest,
+            position: None,
+            commanded_rotation_deg: 0.0,
+            continuous_rotation: None,
+        }
+    }
+
+    /// Move timing for an axis at the current speed.
+    fn axis_timing(&self, axis: Axis) -> AxisTiming {
+        let slowdown = match axis {
+            Axis::Rotation => {
+                REVO_SPEED_LIMITS.fastest.rotation_deg_per_sec / self.speeds.rotation_deg_per_sec
+            }
+            Axis::Tilt => REVO_SPEED_LIMITS.fastest.tilt_deg_per_sec / self.speeds.tilt_deg_per_sec,
+        };
+        self.timing.axis(axis).slowed_by(slowdown)
+    }
+
+    /// Command a rotation towards an absolute angle, returning the relative rotation actually sent.
+    async fn send_rotation_to(&mut self, rotation_deg: f32) -> Result<f64, anyhow::Error> {
+        let rotation = ((rotation_deg as f64 - self.commanded_rotation_deg) / ANGLE_RESOLUTION_DEG)
+            .round()
+            * ANGLE_RESOLUTION_DEG;
+        if rotation != 0.0 {
+            self.transport
+                .send_command(&Command::RotateBy(rotation as f32))
+                .await?;
+            self.commanded_rotation_deg += rotation;
+        }
+        Ok(rotation)
+    }
 }
 
-pub trait Turntable: Sized + Send + Sync + 'static {
-    async fn connect() -> Result<Self, anyhow::Error>;
+/// A connected turntable. Drivers are constructed through [`DriverSettings::connect`].
+#[async_trait]
+pub trait Turntable: Send + Sync + 'static {
     async fn disconnect(&mut self) -> Result<(), anyhow::Error>;
     async fn configure(&mut self) -> Result<(), anyhow::Error>;
+    /// Return both axes to their home position, which becomes the origin [`Pose`].
     async fn reset_pos(&mut self) -> Result<(), anyhow::Error>;
-    async fn reset_tilt(&mut self) -> Result<(), anyhow::Error>;
-    async fn step_horizontal(&mut self, horizontal_steps: u16) -> Result<(), anyhow::Error>;
-    async fn step_tilt(
-        &mut self,
-        old_position_deg: f32,
-        new_position_deg: f32,
-    ) -> Result<(), anyhow::Error>;
-}
+    /// Move to an absolute pose, waiting until the move has completed.
+    async fn move_to(&mut self, rotation_deg: f32, tilt_deg: f32) -> Result<(), anyhow::Error>;
+    /// Current position of the table, or `None` if it is unknown (e.g. after [`Turntable::stop`]).
+    fn position(&self) -> Option<Pose>;
+    /// Furthest the table tilts either side of level, in degrees.
+    fn max_tilt_deg(&self) -> f32;
+    /// Halt both axes. The position is unknown afterwards, until reset.
+    async fn stop(&mut self) -> Result<(), anyhow::Error>;
+    /// Set the speeds used by subsequent moves, rejecting any outside the driver's [`SpeedLimits`].
+    async fn set_speeds(&mut self, speeds: MotionSpeeds) -> Result<(), anyhow::Error>;
+    /// Start rotating continuously, to the right if `direction` is positive or left if negative.
+    /// Returns once the rotation has started; it carries on until [`Turntable::stop_continuous_rotation`].
+    async fn start_continuous_rotation(&mut self, direction: i8) -> Result<(), anyhow::Error>;
+    /// Stop a continuous rotation, updating the position with where the table stopped.
+    async fn stop_continuous_rotation(&mut self) -> Result<(), anyhow::Error>;
 
-impl Turntable for RevoTurntable {
-    async fn connect() -> Result<Self, anyhow::Error> {
-        let ble = ble::RevopointBLE::connect().await?;
-        Ok(Self { ble })
+    /// Move-time model used to wait out moves, at the speeds set by [`Turntable::configure`].
+    /// `None` for drivers which detect when moves complete, and so need no calibration.
+    fn timing(&self) -> Option<MotionTiming> {
+        None
+    }
+    /// Replace the move-time model, and save it for this device.
+    fn set_timing(&mut self, _timing: MotionTiming) -> Result<(), anyhow::Error> {
+        Err(anyhow!("This driver doesn't use a timing model"))
+    }
+    /// Start a relative move on one axis without waiting for it to complete, so it can be timed.
+    async fn start_move(&mut self, _axis: Axis, _degrees: f32) -> Result<(), anyhow::Error> {
+        Err(anyhow!("This driver doesn't support timed moves"))
+    }
+    /// Ask the device for its current rotation angle.
+    /// Returns `None` if angle queries aren't supported, or no reply was received.
+    async fn query_angle(&mut self) -> Result<Option<f32>, anyhow::Error> {
+        Ok(None)
+    }
+    /// Send a raw protocol message, then wait briefly for any replies so they reach the traffic log.
+    async fn send_raw(&mut self, _message: &str) -> Result<(), anyhow::Error> {
+        Err(anyhow!("This driver doesn't support raw messages"))
     }
+}
 
+#[async_trait]
+impl<T: Transport> Turntable for RevoTurntable<T> {
     async fn disconnect(&mut self) -> Result<(), anyhow::Error> {
-        self.ble.disconnect().await
+        self.transport.disconnect().await
     }
 
     async fn configure(&mut self) -> Result<(), anyhow::Error> {
-        self.ble
-            .send_command(&Command::SetRotationSpeed(ROTATION_PACE))
-            .await?;
-        self.ble
-            .send_command(&Command::SetTiltSpeed(TILT_PACE))
-            .await?;
+        self.set_speeds(REVO_SPEED_LIMITS.fastest).await?;
         sleep(Duration::from_millis(100)).await;
         Ok(())
     }
 
     async fn reset_pos(&mut self) -> Result<(), anyhow::Error> {
-        self.ble.send_command(&Command::ZeroRotation).await?;
-        self.ble.send_command(&Command::ZeroTilt).await?;
-        let sleep_duration: u64 = max((ROTATION_PACE * 500.0) as u64, 3500);
-        sleep(Duration::from_millis(sleep_duration)).await;
+        self.transport.send_command(&Command::ZeroRotation).await?;
+        self.transport.send_command(&Command::ZeroTilt).await?;
+        // Allow for up to half a turn back to zero, and tilting back from the furthest extent
+        sleep(
+            self.axis_timing(Axis::Rotation)
+                .estimate(180.0)
+                .max(self.axis_timing(Axis::Tilt).estimate(self.max_tilt_deg())),
+        )
+        .await;
+        self.position = Some(Pose::default());
+        self.commanded_rotation_deg = 0.0;
         Ok(())
     }
 
-    async fn reset_tilt(&mut self) -> Result<(), anyhow::Error> {
-        self.ble.send_command(&Command::ZeroTilt).await?;
-        sleep(Duration::from_millis(3500)).await;
+    async fn move_to(&mut self, rotation_deg: f32, tilt_deg: f32) -> Result<(), anyhow::Error> {
+        let from = self
+            .position
+            .ok_or(anyhow!("Turntable position unknown, reset position first"))?;
+        // Rotation is only commanded relatively, tilt absolutely
+        let rotation = self.send_rotation_to(rotation_deg).await?;
+        if rotation != 0.0 {
+            sleep(self.axis_timing(Axis::Rotation).estimate(rotation as f32)).await;
+        }
+        self.position = Some(Pose {
+            rotation_deg,
+            tilt_deg: from.tilt_deg,
+        });
+        if tilt_deg != from.tilt_deg {
+            self.transport
+                .send_command(&Command::TiltTo(tilt_deg))
+                .await?;
+            sleep(
+                self.axis_timing(Axis::Tilt)
+                    .estimate(tilt_deg - from.tilt_deg),
+            )
+            .await;
+        }
+        self.position = Some(Pose {
+            rotation_deg,
+            tilt_deg,
+        });
+        Ok(())
+    }
+
+    fn position(&self) -> Option<Pose> {
+        self.position
+    }
+
+    fn max_tilt_deg(&self) -> f32 {
+        REVO_MAX_TILT_DEG
+    }
+
+    async fn stop(&mut self) -> Result<(), anyhow::Error> {
+        self.position = None;
+        self.transport.send_command(&Command::StopRotation).await?;
+        self.transport.send_command(&Command::StopTilt).await?;
         Ok(())
     }
 
-    async fn step_horizontal(&mut self, horizontal_steps: u16) -> Result<(), anyhow::Error> {
-        self.ble
-            .send_command(&Command::RotateBy(360.0 / (horizontal_steps as f32)))
+    async fn start_continuous_rotation(&mut self, direction: i8) -> Result<(), anyhow::Error> {
+        self.transport
+            .send_command(&Command::ContinuousRotation(direction.signum()))
             .await?;
-        sleep(Duration::from_millis(
-            (1000.0 * ROTATION_PACE / horizontal_steps as f32) as u64,
-        ))
-        .await;
+        self.continuous_rotation = Some((direction.signum(), Instant::now()));
         Ok(())
     }
 
-    async fn step_tilt(
-        &mut self,
-        old_position_deg: f32,
-        new_position_deg: f32,
-    ) -> Result<(), anyhow::Error> {
-        self.ble
-            .send_command(&Command::TiltTo(new_position_deg))
+    async fn stop_continuous_rotation(&mut self) -> Result<(), anyhow::Error> {
+        self.transport.send_command(&Command::StopRotation).await?;
+        let Some((direction, start)) = self.continuous_rotation.take() else {
+            return Ok(());
+        };
+        // The table can't report how far it went, so estimate from the move timing
+        let timing = self.axis_timing(Axis::Rotation);
+        let elapsed_ms = start.elapsed().as_secs_f32() * 1000.0;
+        let degrees = ((elapsed_ms - timing.constant_ms).max(0.0) / timing.ms_per_deg) as f64;
+        let rotation =
+            (direction as f64 * degrees / ANGLE_RESOLUTION_DEG).round() * ANGLE_RESOLUTION_DEG;
+        if let Some(position) = self.position.as_mut() {
+            self.commanded_rotation_deg += rotation;
+            position.rotation_deg += rotation as f32;
+        }
+        Ok(())
+    }
+
+    async fn set_speeds(&mut self, speeds: MotionSpeeds) -> Result<(), anyhow::Error> {
+        REVO_SPEED_LIMITS.validate(&speeds)?;
+        self.transport
+            .send_command(&Command::SetRotationSpeed(
+                360.0 / speeds.rotation_deg_per_sec,
+            ))
+            .await?;
+        self.transport
+            .send_command(&Command::SetTiltSpeed(
+                TILT_PACE_DEG_PER_SEC / speeds.tilt_deg_per_sec,
+            ))
             .await?;
-        let step_degrees = (new_position_deg - old_position_deg).abs();
-        sleep(Duration::from_millis(
-            (7000.0 / (60.0 / step_degrees)) as u64,
-        ))
+        self.speeds = speeds;
+        Ok(())
+    }
+
+    fn timing(&self) -> Option<MotionTiming> {
+        Some(self.timing)
+    }
+
+    fn set_timing(&mut self, timing: MotionTiming) -> Result<(), anyhow::Error> {
+        self.timing = timing;
+        let dir = self
+            .calibration_dir
+            .as_deref()
+            .ok_or(anyhow!("No config directory available"))?;
+        timing.save(dir, &self.transport.device_id())
+    }
+
+    async fn start_move(&mut self, axis: Axis, degrees: f32) -> Result<(), anyhow::Error> {
+        let mut position = self
+            .position
+            .ok_or(anyhow!("Turntable position unknown, reset position first"))?;
+        match axis {
+            Axis::Rotation => {
+                position.rotation_deg += degrees;
+                self.send_rotation_to(position.rotation_deg).await?;
+            }
+            Axis::Tilt => {
+                position.tilt_deg += degrees;
+                self.transport
+                    .send_command(&Command::TiltTo(position.tilt_deg))
+                    .await?;
+            }
+        }
+        self.position = Some(position);
+        Ok(())
+    }
+
+    async fn query_angle(&mut self) -> Result<Option<f32>, anyhow::Error> {
+        self.transport.send_command(&Command::QueryAngle).await?;
+        let reply = timeout(QUERY_TIMEOUT, async {
+            // Skip over any unrelated messages
+            loop {
+                let message = self.transport.receive().await?;
+                if let Some(angle) = message.strip_prefix("+DATA=") {
+                    return Ok::<_, anyhow::Error>(angle.trim_end_matches(';').trim().parse()?);
+                }
+            }
+        })
         .await;
+        match reply {
+            Ok(angle) => Ok(Some(angle?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    async fn send_raw(&mut self, message: &str) -> Result<(), anyhow::Error> {
+        let mut message = message.trim().to_string();
+        if !message.ends_with(';') {
+            message.push(';');
+        }
+        self.transport
+            .send_command(&Command::Custom(message))
+            .await?;
+        // Drain replies, so they aren't mistaken for answers to later queries
+        let drain = async { while self.transport.receive().await.is_ok() {} };
+        let _ = timeout(RAW_REPLY_WINDOW, drain).await;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Transport which records the commands sent through it.
+    #[derive(Default)]
+    struct RecordingTransport {
+        sent: Vec<String>,
+    }
+
+    #[async_trait]
+    impl Transport for RecordingTransport {
+        fn device_id(&self) -> String {
+            "recording".to_string()
+        }
+
+        async fn send_command(&mut self, cmd: &Command) -> Result<(), anyhow::Error> {
+            self.sent.push(cmd.to_string());
+            Ok(())
+        }
+
+        async fn receive(&mut self) -> Result<String, anyhow::Error> {
+            std::future::pending().await
+        }
+
+        async fn disconnect(&mut self) -> Result<(), anyhow::Error> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rotation_does_not_drift() {
+        let mut table = RevoTurntable::new(RecordingTransport::default(), None);
+        table.reset_pos().await.unwrap();
+        for step in 1..=3 * 7 {
+            table.move_to(step as f32 * 360.0 / 7.0, 0.0).await.unwrap();
+        }
+        // Total rotation, as the device would parse it, in hundredths of a degree
+        let total: i64 = table
+            .transport
+            .sent
+            .iter()
+            .filter_map(|cmd| cmd.strip_prefix("+CT,TURNANGLE="))
+            .map(|angle| {
+                angle
+                    .trim_end_matches(';')
+                    .replace('.', "")
+                    .parse::<i64>()
+                    .unwrap()
+            })
+            .sum();
+        assert_eq!(total, 3 * 36000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_speeds() {
+        let mut table = RevoTurntable::new(RecordingTransport::default(), None);
+        table.reset_pos().await.unwrap();
+        let speeds = MotionSpeeds {
+            rotation_deg_per_sec: 5.0,
+            tilt_deg_per_sec: TILT_PACE_DEG_PER_SEC / 20.0,
+        };
+        table.set_speeds(speeds).await.unwrap();
+        assert_eq!(
+            table.transport.sent[2..],
+            ["+CT,TURNSPEED=72.00;", "+CR,TILTSPEED=20.00;"]
+        );
+        // Move estimates scale with the slower speed
+        let start = tokio::time::Instant::now();
+        table.move_to(90.0, 0.0).await.unwrap();
+        assert!((start.elapsed().as_secs_f32() - 18.0).abs() < 0.01);
+
+        let too_fast = MotionSpeeds {
+            rotation_deg_per_sec: 20.0,
+            ..speeds
+        };
+        assert!(table.set_speeds(too_fast).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_send_raw_terminates_message() {
+        let mut table = RevoTurntable::new(RecordingTransport::default(), None);
+        table.send_raw(" +CT,STOP ").await.unwrap();
+        table.send_raw("+CR,STOP;").await.unwrap();
+        assert_eq!(table.transport.sent, ["+CT,STOP;", "+CR,STOP;"]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Serial/UART transport for the Revopoint Dual Axis Turntable using tokio-serial.
+//!
+//! Useful for driving the table through a wired UART bridge or a BLE-to-serial dongle.
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+use super::command::Command;
+use super::transport::{TrafficKind, TrafficLog, Transport};
+
+/// Default baud rate of the turntable's UART module.
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+
+/// Wrapper around an open serial port connected to a turntable.
+#[derive(Debug)]
+pub struct RevopointSerial {
+    port: SerialStream,
+    path: String,
+    traffic: TrafficLog,
+}
+
+impl RevopointSerial {
+    /// Open the serial port at `path`, or the first serial port found if `path` is empty.
+    /// All messages are reported to `traffic`.
+    pub fn open(path: &str, baud_rate: u32, traffic: TrafficLog) -> Result<Self, anyhow::Error> {
+        let path = resolve_port_path(path)?;
+        let port = tokio_serial::new(&path, baud_rate).open_native_async()?;
+        Ok(RevopointSerial {
+            port,
+            path,
+            traffic,
+        })
+    }
+}
+
+/// `path` itself, or the first serial port found if `path` is empty.
+pub(super) fn resolve_port_path(path: &str) -> Result<String, anyhow::Error> {
+    if !path.is_empty() {
+        return Ok(path.to_string());
+    }
+    Ok(tokio_serial::available_ports()?
+        .into_iter()
+        .next()
+        .ok_or(anyhow!("No serial ports found"))?
+        .port_name)
+}
+
+#[async_trait]
+impl Transport for RevopointSerial {
+    fn device_id(&self) -> String {
+        self.path.clone()
+    }
+
+    /// Send a command to the turntable over the serial port.
+    async fn send_command(&mut self, cmd: &Command) -> Result<(), anyhow::Error> {
+        let data = cmd.to_string();
+        self.traffic.record(TrafficKind::Sent, &data);
+        self.port.write_all(data.as_bytes()).await?;
+        self.port.flush().await?;
+        Ok(())
+    }
+
+    /// Read bytes from the serial port up to the next `;`.
+    /// Messages are only logged once read, as nothing reads the port in the background.
+    async fn receive(&mut self) -> Result<String, anyhow::Error> {
+        let mut data = Vec::new();
+        loop {
+            let byte = self.port.read_u8().await?;
+            data.push(byte);
+            if byte == b';' {
+                let message = String::from_utf8_lossy(&data).into_owned();
+                self.traffic.record(TrafficKind::Received, &message);
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Nothing to tear down; the port is closed when dropped.
+    async fn disconnect(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+/// A fake turntable on the far end of a pseudo-terminal, for exercising serial transports in tests.
+#[cfg(test)]
+pub(crate) mod fake {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_serial::SerialStream;
+
+    use super::RevopointSerial;
+
+    pub(crate) struct FakeDevice {
+        pub(crate) port: SerialStream,
+    }
+
+    impl FakeDevice {
+        /// Create a fake device, and the host end of the port connected to it.
+        pub(crate) fn pair_stream() -> Result<(Self, SerialStream), anyhow::Error> {
+            let (device, host) = SerialStream::pair()?;
+            Ok((FakeDevice { port: device }, host))
+        }
+
+        /// Create a fake device, and a transport connected to it.
+        pub(crate) fn pair() -> Result<(Self, RevopointSerial), anyhow::Error> {
+            let (device, host) = Self::pair_stream()?;
+            Ok((
+                device,
+                RevopointSerial {
+                    port: host,
+                    path: "fake".to_string(),
+                    traffic: Default::default(),
+                },
+            ))
+        }
+
+        /// Write a raw reply back to the host.
+        pub(crate) async fn send(&mut self, data: &str) -> Result<(), anyhow::Error> {
+            self.port.write_all(data.as_bytes()).await?;
+            Ok(())
+        }
+
+        /// Read a single byte sent by the host, e.g. a realtime command.
+        pub(crate) async fn recv_byte(&mut self) -> Result<u8, anyhow::Error> {
+            Ok(self.port.read_u8().await?)
+        }
+
+        /// Read bytes up to and including the next `terminator`.
+        pub(crate) async fn recv_until(&mut self, terminator: u8) -> Result<String, anyhow::Error> {
+            let mut data = Vec::new();
+            loop {
+                let byte = self.port.read_u8().await?;
+                data.push(byte);
+                if byte == terminator {
+                    return Ok(String::from_utf8(data)?);
+                }
+            }
+        }
+
+        /// Read the next `;`-terminated command sent by the host.
+        pub(crate) async fn recv_command(&mut self) -> Result<String, anyhow::Error> {
+            self.recv_until(b';').await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeDevice;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_command() {
+        let (mut device, mut transport) = FakeDevice::pair().unwrap();
+        transport
+            .send_command(&Command::RotateBy(-30.5))
+            .await
+            .unwrap();
+        transport.send_command(&Command::ZeroTilt).await.unwrap();
+        assert_eq!(
+            device.recv_command().await.unwrap(),
+            "+CT,TURNANGLE=-30.50;"
+        );
+        assert_eq!(device.recv_command().await.unwrap(), "+CR,TOZERO;");
+    }
+}
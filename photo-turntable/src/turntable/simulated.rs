@@ -0,0 +1,126 @@
+//! Simulated turntable, for trying out jobs and the UI without hardware attached.
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+use super::{MotionSpeeds, Pose, SpeedLimits, Turntable};
+
+/// Slowest speed a job may request, in degrees/second.
+const MIN_SPEED: f32 = 1.0;
+/// Furthest the simulated table tilts either side of level.
+pub const MAX_TILT_DEG: f32 = 90.0;
+
+/// A turntable which takes time to "move" at the configured speeds, but drives nothing.
+#[derive(Debug)]
+pub struct SimulatedTurntable {
+    limits: SpeedLimits,
+    /// Current speed, in degrees/second
+    rotation_speed: f32,
+    /// Current speed, in degrees/second
+    tilt_speed: f32,
+    position: Option<Pose>,
+    /// Direction and start time of any continuous rotation in progress
+    continuous_rotation: Option<(i8, Instant)>,
+}
+
+impl SimulatedTurntable {
+    /// Create a turntable which moves at up to the given speeds, in degrees/second.
+    pub fn new(rotation_speed: f32, tilt_speed: f32) -> Self {
+        Self {
+            limits: Self::speed_limits(rotation_speed, tilt_speed),
+            rotation_speed,
+            tilt_speed,
+            position: Some(Pose::default()),
+            continuous_rotation: None,
+        }
+    }
+
+    /// Speeds a table created with the given top speeds can be driven at.
+    pub fn speed_limits(rotation_speed: f32, tilt_speed: f32) -> SpeedLimits {
+        SpeedLimits {
+            slowest: MotionSpeeds {
+                rotation_deg_per_sec: MIN_SPEED,
+                tilt_deg_per_sec: MIN_SPEED,
+            },
+            fastest: MotionSpeeds {
+                rotation_deg_per_sec: rotation_speed,
+                tilt_deg_per_sec: tilt_speed,
+            },
+        }
+    }
+
+    async fn travel(degrees: f32, speed: f32) {
+        sleep(Duration::from_secs_f32(degrees.abs() / speed)).await;
+    }
+}
+
+#[async_trait]
+impl Turntable for SimulatedTurntable {
+    async fn disconnect(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn configure(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn reset_pos(&mut self) -> Result<(), anyhow::Error> {
+        if let Some(position) = self.position {
+            let unwind = -(position.rotation_deg + 180.0).rem_euclid(360.0) + 180.0;
+            Self::travel(unwind, self.rotation_speed).await;
+            Self::travel(position.tilt_deg, self.tilt_speed).await;
+        }
+        self.position = Some(Pose::default());
+        Ok(())
+    }
+
+    async fn move_to(&mut self, rotation_deg: f32, tilt_deg: f32) -> Result<(), anyhow::Error> {
+        let from = self
+            .position
+            .ok_or(anyhow!("Turntable position unknown, reset position first"))?;
+        Self::travel(rotation_deg - from.rotation_deg, self.rotation_speed).await;
+        Self::travel(tilt_deg - from.tilt_deg, self.tilt_speed).await;
+        self.position = Some(Pose {
+            rotation_deg,
+            tilt_deg,
+        });
+        Ok(())
+    }
+
+    fn position(&self) -> Option<Pose> {
+        self.position
+    }
+
+    fn max_tilt_deg(&self) -> f32 {
+        MAX_TILT_DEG
+    }
+
+    async fn stop(&mut self) -> Result<(), anyhow::Error> {
+        self.position = None;
+        Ok(())
+    }
+
+    async fn start_continuous_rotation(&mut self, direction: i8) -> Result<(), anyhow::Error> {
+        self.continuous_rotation = Some((direction.signum(), Instant::now()));
+        Ok(())
+    }
+
+    async fn stop_continuous_rotation(&mut self) -> Result<(), anyhow::Error> {
+        if let (Some((direction, start)), Some(position)) =
+            (self.continuous_rotation.take(), self.position.as_mut())
+        {
+            position.rotation_deg +=
+                direction as f32 * start.elapsed().as_secs_f32() * self.rotation_speed;
+        }
+        Ok(())
+    }
+
+    async fn set_speeds(&mut self, speeds: MotionSpeeds) -> Result<(), anyhow::Error> {
+        self.limits.validate(&speeds)?;
+        self.rotation_speed = speeds.rotation_deg_per_sec;
+        self.tilt_speed = speeds.tilt_deg_per_sec;
+        Ok(())
+    }
+}
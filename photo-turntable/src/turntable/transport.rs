@@ -0,0 +1,60 @@
+//! Transport abstraction for the Revopoint ASCII command protocol.
+
+use async_trait::async_trait;
+use std::time::SystemTime;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::command::Command;
+
+/// What a [`Traffic`] entry records.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrafficKind {
+    Sent,
+    Received,
+    /// A failure to send or receive
+    Error,
+}
+
+/// A message sent to or received from a turntable, for the protocol console.
+#[derive(Debug, Clone)]
+pub struct Traffic {
+    pub time: SystemTime,
+    pub kind: TrafficKind,
+    pub message: String,
+}
+
+/// Where transports report the messages passing through them. The default log discards everything.
+#[derive(Debug, Clone, Default)]
+pub struct TrafficLog(Option<UnboundedSender<Traffic>>);
+
+impl TrafficLog {
+    pub fn new(traffic_tx: UnboundedSender<Traffic>) -> Self {
+        Self(Some(traffic_tx))
+    }
+
+    /// Record a message, timestamped now. Nothing listening is fine, so failures are ignored.
+    pub fn record(&self, kind: TrafficKind, message: &str) {
+        if let Some(traffic_tx) = &self.0 {
+            let _ = traffic_tx.send(Traffic {
+                time: SystemTime::now(),
+                kind,
+                message: message.to_string(),
+            });
+        }
+    }
+}
+
+/// A link capable of delivering [`Command`]s to a Revopoint turntable.
+///
+/// The protocol is plain ASCII, so it can be carried over BLE or any byte stream (e.g. a UART bridge).
+#[async_trait]
+pub trait Transport: Send + Sync + 'static {
+    /// Stable identifier for the connected device, e.g. its Bluetooth address.
+    fn device_id(&self) -> String;
+    /// Send a single command to the turntable.
+    async fn send_command(&mut self, cmd: &Command) -> Result<(), anyhow::Error>;
+    /// Wait for the next `;`-terminated message from the turntable (e.g. `+DATA=12.34;`).
+    async fn receive(&mut self) -> Result<String, anyhow::Error>;
+    /// Close the connection.
+    async fn disconnect(&mut self) -> Result<(), anyhow::Error>;
+}